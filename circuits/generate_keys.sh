@@ -1,6 +1,6 @@
 #!/bin/bash
 
-contracts=("deposit" "migrate" "withdraw")
+contracts=("deposit" "migrate" "withdraw" "reclaim")
 
 for contract in "${contracts[@]}"
 do
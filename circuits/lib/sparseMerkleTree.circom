@@ -0,0 +1,36 @@
+pragma circom 2.0.0;
+
+include "../circomlib/circuits/smt/smtverifier.circom";
+
+// Verifies a proof from `lib::sparse_merkle_tree` for a tree with the given root.
+// The tree is laid out like circomlib's `smt.js`, so this is its SMTVerifier.
+// `siblings` are the proof's siblings padded with zeros.
+// if fnc == 0 checks that key holds value
+// if fnc == 1 checks that key isn't in the tree: its path ends at an empty node
+// (isOld0 == 1) or at the leaf of another key oldKey with value oldValue (isOld0 == 0)
+template SparseMerkleTreeChecker(levels) {
+    signal input root;
+    signal input key;
+    signal input value;
+    signal input fnc;
+    signal input oldKey;
+    signal input oldValue;
+    signal input isOld0;
+    signal input siblings[levels];
+
+    fnc * (1 - fnc) === 0;
+    isOld0 * (1 - isOld0) === 0;
+
+    component verifier = SMTVerifier(levels);
+    verifier.enabled <== 1;
+    verifier.root <== root;
+    verifier.key <== key;
+    verifier.value <== value;
+    verifier.fnc <== fnc;
+    verifier.oldKey <== oldKey;
+    verifier.oldValue <== oldValue;
+    verifier.isOld0 <== isOld0;
+    for (var i = 0; i < levels; i++) {
+        verifier.siblings[i] <== siblings[i];
+    }
+}
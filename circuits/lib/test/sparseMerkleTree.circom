@@ -0,0 +1,6 @@
+pragma circom 2.0.0;
+
+include "../sparseMerkleTree.circom";
+
+// Small tree so keys sharing their low bits are easy to produce in test inputs
+component main {public [root, key, value, fnc]} = SparseMerkleTreeChecker(8);
@@ -0,0 +1,37 @@
+#!/bin/bash
+
+# Checks proofs from `lib::sparse_merkle_tree` against the circuit.
+# The inputs are committed, and `cargo run` in `generate-data` regenerates them.
+# Inputs named `invalid_*` must fail witness generation, all others must pass.
+
+cd "$(dirname "$0")"
+inputs="../../../generate-data/outputs/smt"
+
+mkdir -p build
+circom sparseMerkleTree.circom --wasm -o build || exit 1
+
+failed=0
+for input in "$inputs"/*.json
+do
+  name=$(basename "$input" .json)
+  if node build/sparseMerkleTree_js/generate_witness.js build/sparseMerkleTree_js/sparseMerkleTree.wasm "$input" build/witness.wtns > /dev/null 2>&1; then
+    passed=1
+  else
+    passed=0
+  fi
+
+  if [[ "$name" == invalid_* ]]; then
+    expected=0
+  else
+    expected=1
+  fi
+
+  if [ "$passed" -eq "$expected" ]; then
+    echo "ok   $name"
+  else
+    echo "FAIL $name"
+    failed=1
+  fi
+done
+
+exit $failed
@@ -9,10 +9,16 @@ template Deposit() {
   // Public
   signal input walletAddress;
   signal input credential;
+  signal input tierId;
+
+  // Credentials are bound to a tier, so they can't be migrated out of another tier's tree
+  component secretHasher = Poseidon(2);
+  secretHasher.inputs[0] <== walletAddress;
+  secretHasher.inputs[1] <== secret;
 
   component credentialHasher = Poseidon(2);
-  credentialHasher.inputs[0] <== walletAddress;
-  credentialHasher.inputs[1] <== secret;
+  credentialHasher.inputs[0] <== tierId;
+  credentialHasher.inputs[1] <== secretHasher.out;
 
   // log("walletAddress", walletAddress);
   // log("secret", secret);
@@ -23,5 +29,5 @@ template Deposit() {
 }
 
 component main {
-    public [walletAddress, credential]
+    public [walletAddress, credential, tierId]
 } = Deposit();
\ No newline at end of file
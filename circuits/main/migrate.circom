@@ -17,12 +17,17 @@ template Swap(merkleTreeHeight) {
   signal input depositTreeRoot;
   signal input nullifier;
   signal input previousNullifier;
+  signal input tierId;
 
   // Verify membership of deposit credential in the deposit tree
   signal depositCredential;
+  component depositSecretHasher = Poseidon(2);
+  depositSecretHasher.inputs[0] <== walletAddress;
+  depositSecretHasher.inputs[1] <== secret;
+
   component depositCredentialHasher = Poseidon(2);
-  depositCredentialHasher.inputs[0] <== walletAddress;
-  depositCredentialHasher.inputs[1] <== secret;
+  depositCredentialHasher.inputs[0] <== tierId;
+  depositCredentialHasher.inputs[1] <== depositSecretHasher.out;
   depositCredential <== depositCredentialHasher.out;
 
   component tree = MerkleTreeChecker(merkleTreeHeight);
@@ -64,5 +69,5 @@ template Swap(merkleTreeHeight) {
 }
 
 component main {
-    public [depositTreeRoot, nullifier, previousNullifier]
+    public [depositTreeRoot, nullifier, previousNullifier, tierId]
 } = Swap(20);
\ No newline at end of file
@@ -0,0 +1,36 @@
+pragma circom 2.0.0;
+
+include "../circomlib/circuits/poseidon.circom";
+
+template Reclaim() {
+  // Private
+  signal input secret;
+
+  // Public
+  signal input walletAddress;
+  signal input credential;
+  signal input nullifier;
+  signal input tierId;
+
+  // Verify the credential was made from the secret. The credential is public,
+  // as a reclaim pays out to the withdraw address bound at deposit
+  component secretHasher = Poseidon(2);
+  secretHasher.inputs[0] <== walletAddress;
+  secretHasher.inputs[1] <== secret;
+
+  component credentialHasher = Poseidon(2);
+  credentialHasher.inputs[0] <== tierId;
+  credentialHasher.inputs[1] <== secretHasher.out;
+  credential === credentialHasher.out;
+
+  // Spend the same nullifier as a migration, so a deposit is either migrated or reclaimed
+  component nullifierHasher = Poseidon(3);
+  nullifierHasher.inputs[0] <== walletAddress;
+  nullifierHasher.inputs[1] <== secret;
+  nullifierHasher.inputs[2] <== 1;
+  nullifier === nullifierHasher.out;
+}
+
+component main {
+    public [walletAddress, credential, nullifier, tierId]
+} = Reclaim();
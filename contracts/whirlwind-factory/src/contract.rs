@@ -0,0 +1,452 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, StdError,
+    StdResult, SubMsg, Uint128, WasmMsg,
+};
+use cw2::set_contract_version;
+use cw_storage_plus::Bound;
+use cw_utils::parse_reply_instantiate_data;
+use whirlwind_swap::msg::{
+    DenomUnvalidated, InstantiateMsg as PoolInstantiateMsg, QueryMsg as PoolQueryMsg, TierMsg,
+    TiersResponse,
+};
+
+use crate::error::ContractError;
+use crate::msg::{
+    CreatePoolMsg, ExecuteMsg, InstantiateMsg, PoolResponse, PoolsResponse, QueryMsg,
+    UpdateConfigMsg,
+};
+use crate::state::{
+    denom_key, Config, PendingPool, Pool, CONFIG, NEXT_POOL_ID, PENDING_POOL, POOLS,
+    POOLS_BY_DENOM, VK_DEPOSIT, VK_RECLAIM, VK_SWAP_DEPOSIT, VK_WITHDRAW,
+};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:whirlwind-factory";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const INSTANTIATE_POOL_REPLY_ID: u64 = 1;
+
+const DEFAULT_QUERY_LIMIT: u32 = 10;
+const MAX_QUERY_LIMIT: u32 = 30;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let owner = match msg.owner {
+        Some(owner) => deps.api.addr_validate(&owner)?,
+        None => info.sender,
+    };
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            owner,
+            swap_code_id: msg.swap_code_id,
+        },
+    )?;
+    VK_DEPOSIT.save(deps.storage, &msg.vk_deposit)?;
+    VK_SWAP_DEPOSIT.save(deps.storage, &msg.vk_swap_deposit)?;
+    VK_WITHDRAW.save(deps.storage, &msg.vk_withdraw)?;
+    VK_RECLAIM.save(deps.storage, &msg.vk_reclaim)?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreatePool(pool) => execute_create_pool(deps, env, info, pool),
+        ExecuteMsg::UpdateConfig(update) => execute_update_config(deps, info, update),
+    }
+}
+
+pub fn execute_create_pool(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pool: CreatePoolMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let CreatePoolMsg {
+        denom,
+        amount,
+        pool_admin,
+        allowed_pools,
+        cw20_wrappers,
+        reclaim_delay_blocks,
+        migration_policy,
+        root_expiry,
+    } = pool;
+
+    // One pool per (denom, amount), so deposits aren't split across anonymity sets
+    let validated_denom = denom.clone().validate(deps.api)?;
+    if POOLS_BY_DENOM.has(deps.storage, (denom_key(&validated_denom), amount.u128())) {
+        return Err(ContractError::DuplicatePool {});
+    }
+    PENDING_POOL.save(
+        deps.storage,
+        &PendingPool {
+            denom: validated_denom,
+            amount,
+        },
+    )?;
+
+    let label = format!("whirlwind {} {}", amount, denom_label(&denom));
+    // The owner can migrate pools to new code. The factory itself never sends migrations
+    let msg = WasmMsg::Instantiate {
+        admin: Some(config.owner.to_string()),
+        code_id: config.swap_code_id,
+        msg: to_binary(&PoolInstantiateMsg {
+            tiers: vec![TierMsg { denom, amount }],
+            pool_admin,
+            allowed_pools,
+            cw20_wrappers,
+            vk_deposit: VK_DEPOSIT.load(deps.storage)?,
+            vk_swap_deposit: VK_SWAP_DEPOSIT.load(deps.storage)?,
+            vk_withdraw: VK_WITHDRAW.load(deps.storage)?,
+            vk_reclaim: VK_RECLAIM.load(deps.storage)?,
+            reclaim_delay_blocks,
+            migration_policy,
+            root_expiry,
+        })?,
+        funds: vec![],
+        label,
+    };
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(msg, INSTANTIATE_POOL_REPLY_ID))
+        .add_attribute("action", "create_pool")
+        .add_attribute("from", info.sender))
+}
+
+pub fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    update: UpdateConfigMsg,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let UpdateConfigMsg {
+        owner,
+        swap_code_id,
+        vk_deposit,
+        vk_swap_deposit,
+        vk_withdraw,
+        vk_reclaim,
+    } = update;
+
+    if let Some(owner) = owner {
+        config.owner = deps.api.addr_validate(&owner)?;
+    }
+    if let Some(swap_code_id) = swap_code_id {
+        config.swap_code_id = swap_code_id;
+    }
+    CONFIG.save(deps.storage, &config)?;
+    if let Some(vk) = vk_deposit {
+        VK_DEPOSIT.save(deps.storage, &vk)?;
+    }
+    if let Some(vk) = vk_swap_deposit {
+        VK_SWAP_DEPOSIT.save(deps.storage, &vk)?;
+    }
+    if let Some(vk) = vk_withdraw {
+        VK_WITHDRAW.save(deps.storage, &vk)?;
+    }
+    if let Some(vk) = vk_reclaim {
+        VK_RECLAIM.save(deps.storage, &vk)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("from", info.sender))
+}
+
+fn denom_label(denom: &DenomUnvalidated) -> &str {
+    match denom {
+        DenomUnvalidated::Native(denom) => denom,
+        DenomUnvalidated::Cw20(addr) => addr,
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_POOL_REPLY_ID => {
+            let res = parse_reply_instantiate_data(msg)?;
+            let addr = deps.api.addr_validate(&res.contract_address)?;
+            let PendingPool { denom, amount } = PENDING_POOL.load(deps.storage)?;
+            PENDING_POOL.remove(deps.storage);
+
+            let pool_id = NEXT_POOL_ID.may_load(deps.storage)?.unwrap_or_default();
+            NEXT_POOL_ID.save(deps.storage, &(pool_id + 1))?;
+            POOLS_BY_DENOM.save(deps.storage, (denom_key(&denom), amount.u128()), &addr)?;
+            POOLS.save(
+                deps.storage,
+                pool_id,
+                &Pool {
+                    addr: addr.clone(),
+                    denom,
+                    amount,
+                },
+            )?;
+
+            Ok(Response::new()
+                .add_attribute("pool_id", pool_id.to_string())
+                .add_attribute("pool_addr", addr))
+        }
+        _ => Err(ContractError::Std(StdError::GenericErr {
+            msg: "Unknown reply ID".to_string(),
+        })),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Pools { start_after, limit } => {
+            to_binary(&query_pools(deps, start_after, limit)?)
+        }
+    }
+}
+
+pub fn query_pools(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PoolsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let pools = POOLS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (pool_id, pool) = item?;
+            let tiers: TiersResponse = deps
+                .querier
+                .query_wasm_smart(pool.addr.to_string(), &PoolQueryMsg::Tiers {})?;
+            // The pool's balance also holds locked balances and swap outputs, so
+            // only deposits that are still unspent count
+            let tvl = tiers
+                .tiers
+                .iter()
+                .map(|tier| {
+                    let unspent = tier.deposit_count.saturating_sub(tier.withdrawal_count);
+                    tier.tier.amount.checked_mul(Uint128::from(unspent))
+                })
+                .try_fold(Uint128::zero(), |tvl, value| tvl.checked_add(value?))?;
+            Ok(PoolResponse {
+                pool_id,
+                addr: pool.addr.to_string(),
+                tvl,
+                deposit_count: tiers.tiers.iter().map(|tier| tier.deposit_count).sum(),
+                denom: pool.denom,
+                amount: pool.amount,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PoolsResponse { pools })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{
+        coins, Addr, ContractResult, CosmosMsg, SubMsgResponse, SubMsgResult, SystemResult,
+        WasmQuery,
+    };
+    use whirlwind_swap::msg::TierResponse;
+    use whirlwind_swap::state::{Denom, Tier};
+
+    // Protobuf-encoded `MsgInstantiateContractResponse` with only an address
+    fn instantiate_response_data(addr: &str) -> Binary {
+        let mut data = vec![0x0a, addr.len() as u8];
+        data.extend_from_slice(addr.as_bytes());
+        Binary::from(data)
+    }
+
+    fn mock_instantiate(deps: DepsMut) {
+        instantiate(
+            deps,
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                owner: None,
+                swap_code_id: 7,
+                vk_deposit: "deposit".into(),
+                vk_swap_deposit: "swap_deposit".into(),
+                vk_withdraw: "withdraw".into(),
+                vk_reclaim: "reclaim".into(),
+            },
+        )
+        .unwrap();
+    }
+
+    fn mock_create_pool_msg(denom: &str, amount: u128) -> CreatePoolMsg {
+        CreatePoolMsg {
+            denom: DenomUnvalidated::Native(denom.into()),
+            amount: Uint128::from(amount),
+            pool_admin: None,
+            allowed_pools: vec![],
+            cw20_wrappers: vec![],
+            reclaim_delay_blocks: 100_000,
+            migration_policy: None,
+            root_expiry: None,
+        }
+    }
+
+    // Instantiates a pool as the owner and registers it at `addr`
+    fn mock_create_pool(mut deps: DepsMut, denom: &str, amount: u128, addr: &str) {
+        execute_create_pool(
+            deps.branch(),
+            mock_env(),
+            mock_info("owner", &[]),
+            mock_create_pool_msg(denom, amount),
+        )
+        .unwrap();
+        reply(
+            deps,
+            mock_env(),
+            Reply {
+                id: INSTANTIATE_POOL_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(instantiate_response_data(addr)),
+                }),
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_create_pool() {
+        let mut deps = mock_dependencies();
+        mock_instantiate(deps.as_mut());
+
+        let create_pool = ExecuteMsg::CreatePool(mock_create_pool_msg("uusdc", 100));
+
+        // Only the owner creates pools
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            create_pool.clone(),
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            create_pool.clone(),
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin,
+                code_id,
+                msg,
+                ..
+            }) => {
+                assert_eq!(admin.as_deref(), Some("owner"));
+                assert_eq!(*code_id, 7);
+                let msg: PoolInstantiateMsg = cosmwasm_std::from_binary(msg).unwrap();
+                assert_eq!(msg.vk_deposit, "deposit");
+                assert_eq!(msg.tiers[0].amount, Uint128::from(100u128));
+            }
+            msg => panic!("Unexpected message {:?}", msg),
+        }
+
+        // Pool is registered once instantiated
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: INSTANTIATE_POOL_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(instantiate_response_data("pool")),
+                }),
+            },
+        )
+        .unwrap();
+        let pool = POOLS.load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(pool.addr, Addr::unchecked("pool"));
+        assert_eq!(pool.denom, Denom::Native("uusdc".into()));
+
+        // Same denom and amount can't be created twice
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            create_pool,
+        );
+        assert!(matches!(res, Err(ContractError::DuplicatePool {})));
+    }
+
+    #[test]
+    fn test_query_pools() {
+        let mut deps = mock_dependencies();
+        mock_instantiate(deps.as_mut());
+        mock_create_pool(deps.as_mut(), "uusdc", 100, "pool0");
+        // Another amount of the same denom is a separate pool
+        mock_create_pool(deps.as_mut(), "uusdc", 1_000, "pool1");
+
+        // pool0 had 5 deposits, of which 2 were migrated or reclaimed. Its balance also
+        // holds what the migrated deposits were swapped into
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } => {
+                let (amount, deposit_count, withdrawal_count) = match contract_addr.as_str() {
+                    "pool0" => (100u128, 5, 2),
+                    _ => (1_000u128, 1, 0),
+                };
+                let res = TiersResponse {
+                    tiers: vec![TierResponse {
+                        tier_id: 0,
+                        tier: Tier {
+                            denom: Denom::Native("uusdc".into()),
+                            amount: Uint128::from(amount),
+                        },
+                        epoch: 0,
+                        deposit_count,
+                        withdrawal_count,
+                    }],
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+            }
+            _ => panic!("Unexpected query {:?}", query),
+        });
+        deps.querier
+            .update_balance("pool0", coins(1_000_000, "uusdc"));
+
+        let res = query_pools(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.pools.len(), 2);
+        assert_eq!(res.pools[0].addr, "pool0");
+        assert_eq!(res.pools[0].tvl, Uint128::from(300u128));
+        assert_eq!(res.pools[0].deposit_count, 5);
+        assert_eq!(res.pools[1].addr, "pool1");
+        assert_eq!(res.pools[1].tvl, Uint128::from(1_000u128));
+
+        let res = query_pools(deps.as_ref(), Some(0), None).unwrap();
+        assert_eq!(res.pools.len(), 1);
+        assert_eq!(res.pools[0].pool_id, 1);
+    }
+}
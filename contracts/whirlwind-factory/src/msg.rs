@@ -0,0 +1,77 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::Uint128;
+use whirlwind_swap::msg::{AllowedPool, Cw20WrapperMsg, DenomUnvalidated};
+use whirlwind_swap::state::{Denom, MigrationPolicy, RootExpiry};
+
+use crate::state::Config;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    // Defaults to the sender
+    pub owner: Option<String>,
+    pub swap_code_id: u64,
+
+    pub vk_deposit: String,
+    pub vk_swap_deposit: String,
+    pub vk_withdraw: String,
+    pub vk_reclaim: String,
+}
+
+#[cw_serde]
+pub struct CreatePoolMsg {
+    pub denom: DenomUnvalidated,
+    pub amount: Uint128,
+    pub pool_admin: Option<String>,
+    pub allowed_pools: Vec<AllowedPool>,
+    pub cw20_wrappers: Vec<Cw20WrapperMsg>,
+    pub reclaim_delay_blocks: u64,
+    pub migration_policy: Option<MigrationPolicy>,
+    pub root_expiry: Option<RootExpiry>,
+}
+
+#[cw_serde]
+pub struct UpdateConfigMsg {
+    pub owner: Option<String>,
+    pub swap_code_id: Option<u64>,
+    pub vk_deposit: Option<String>,
+    pub vk_swap_deposit: Option<String>,
+    pub vk_withdraw: Option<String>,
+    pub vk_reclaim: Option<String>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    // Instantiate a pool taking fixed deposits of `amount` `denom`
+    CreatePool(CreatePoolMsg),
+    // Only affects pools created afterwards
+    UpdateConfig(UpdateConfigMsg),
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(Config)]
+    Config {},
+    // Registered pools in id order, with their current TVL and deposit count
+    #[returns(PoolsResponse)]
+    Pools {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct PoolResponse {
+    pub pool_id: u64,
+    pub addr: String,
+    pub denom: Denom,
+    pub amount: Uint128,
+    // Value of the deposits not yet migrated or reclaimed
+    pub tvl: Uint128,
+    pub deposit_count: u32,
+}
+
+#[cw_serde]
+pub struct PoolsResponse {
+    pub pools: Vec<PoolResponse>,
+}
@@ -0,0 +1,49 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Uint128};
+use cw_storage_plus::{Item, Map};
+use whirlwind_swap::state::Denom;
+
+#[cw_serde]
+pub struct Config {
+    pub owner: Addr,
+    // Code id of `whirlwind-swap` that new pools are instantiated from
+    pub swap_code_id: u64,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// Verifying keys passed to every new pool. Stored apart from the config as
+// they are several kilobytes each
+pub const VK_DEPOSIT: Item<String> = Item::new("vk_deposit");
+pub const VK_SWAP_DEPOSIT: Item<String> = Item::new("vk_swap_deposit");
+pub const VK_WITHDRAW: Item<String> = Item::new("vk_withdraw");
+pub const VK_RECLAIM: Item<String> = Item::new("vk_reclaim");
+
+#[cw_serde]
+pub struct Pool {
+    pub addr: Addr,
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+// Registry of instantiated pools, keyed by pool id
+pub const POOLS: Map<u64, Pool> = Map::new("pools");
+pub const NEXT_POOL_ID: Item<u64> = Item::new("next_pool_id");
+// Pool address of each (denom, amount), keyed by `denom_key`
+pub const POOLS_BY_DENOM: Map<(String, u128), Addr> = Map::new("pools_by_denom");
+
+pub fn denom_key(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => format!("native:{}", denom),
+        Denom::Cw20(addr) => format!("cw20:{}", addr),
+    }
+}
+
+// Pool being instantiated, saved to the registry in the reply
+#[cw_serde]
+pub struct PendingPool {
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+pub const PENDING_POOL: Item<PendingPool> = Item::new("pending_pool");
@@ -5,24 +5,38 @@ use ark_groth16::Proof;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Reply, StdError,
-    StdResult, Storage, SubMsg, Uint128, Uint256, WasmMsg,
+    from_binary, to_binary, Addr, Api, BankMsg, Binary, Coin, Decimal256, Deps, DepsMut, Env,
+    Event, MessageInfo, Order, Reply, StdError, StdResult, Storage, Timestamp, Uint128, Uint256,
+    WasmMsg,
 };
 use cw2::set_contract_version;
-use cw20::Cw20ExecuteMsg;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_storage_plus::Bound;
+use lib::merkle_tree::{verify_path, MerkleTreeWithHistory};
 use lib::msg::PublicSignals;
 
 use crate::error::ContractError;
 use crate::msg::{
-    DenomUnvalidated, ExecuteMsg, InstantiateMsg, OsmosisRoute, OsmosisSwap, OsmosisSwapValue,
-    QueryMsg,
+    AllowedPool, BatchDeposit, Cw20ConverterExecuteMsg, Cw20ConverterHookMsg, Cw20WrapperMsg,
+    DcaScheduleMsg, DcaScheduleResponse, DcaSchedulesResponse, DenomUnvalidated, DepositMsg,
+    DepositResponse, ExecuteMsg, InstantiateMsg, LimitOrderMsg, LimitOrderResponse,
+    LimitOrdersResponse, MigrateMsg, OsmosisRoute, OsmosisSwap, QueryMsg, ReceiveMsg,
+    RootInfoResponse, SimulateSwapResponse, SwapMsg, TierResponse, TiersResponse,
+    VerifyPathResponse,
 };
+use crate::osmosis::{parse_pool_id, query_arithmetic_twap_to_now};
 use crate::state::{
-    AmountDenom, Denom, SwapContext, ALLOWED_POOLS, COMMITMENTS, DEPOSIT_AMOUNT, DEPOSIT_DENOM,
-    DEPOSIT_VERIFIER, MAP_ADDR_TO_LOCKED_BALANCES, MAP_ADDR_TO_PREVIOUS_NULLIFIER,
-    MIGRATE_VERIFIER, NULLIFIERS, POOL_ADMIN, SWAP_CTX, WITHDRAW_VERIFIER,
+    AmountDenom, Cw20Unwrap, Cw20Wrapper, DcaInterval, DcaSchedule, Denom, DepositInfo, KeeperTip,
+    LimitOrder, MigrationPolicy, PoolVenue, RootExpiry, RootInfo, SwapContext, Tier, ALLOWED_POOLS,
+    COMMITMENTS, CREDENTIALS, CURRENT_EPOCHS, CW20_WRAPPERS, DCA_SCHEDULES, DCA_SCHEDULES_BY_OWNER,
+    DEPOSIT_VERIFIER, EPOCH_TREES, LEGACY_ALLOWED_POOLS, LEGACY_COMMITMENT_TREE,
+    LEGACY_DEPOSIT_AMOUNT, LEGACY_DEPOSIT_DENOM, LEGACY_MIGRATE_VERIFIER, LEGACY_NULLIFIERS,
+    LIMIT_ORDERS, MAP_ADDR_TO_LOCKED_BALANCES, MAP_ADDR_TO_PREVIOUS_NULLIFIER, MIGRATE_VERIFIER,
+    MIGRATION_POLICY, NEXT_DCA_SCHEDULE_ID, NEXT_LIMIT_ORDER_ID, NEXT_TREE_ID, NULLIFIERS,
+    POOL_ADMIN, RECLAIM_DELAY_BLOCKS, RECLAIM_VERIFIER, ROOT_EXPIRY, ROOT_INFO, SWAP_QUEUE, TIERS,
+    WITHDRAWAL_COUNTS, WITHDRAW_VERIFIER,
 };
-use lib::merkle_tree::MerkleTreeWithHistory;
+use crate::venue::{route_denom, SwapSimulation};
 use lib::verifier::Verifier;
 
 // version info for migration info
@@ -31,12 +45,25 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const SWAP_REPLY_ID: u64 = 1;
 
+// Levels of the commitment trees, fixed by the circuits
+const TREE_LEVELS: u32 = 20;
+
+// Window over which the TWAP used for slippage protection is averaged
+const TWAP_WINDOW_SECONDS: u64 = 600;
+const MAX_BPS: u64 = 10_000;
+// Share of a limit order's output a keeper can be tipped
+const MAX_KEEPER_TIP_BPS: u64 = 500;
+
+const DEFAULT_QUERY_LIMIT: u32 = 10;
+const MAX_QUERY_LIMIT: u32 = 30;
+
 type Response = cosmwasm_std::Response<OsmosisSwap>;
 type CosmosMsg = cosmwasm_std::CosmosMsg<OsmosisSwap>;
+type SubMsg = cosmwasm_std::SubMsg<OsmosisSwap>;
 
 pub fn poseidon_hash(input: &str) -> String {
     // TODO(!): Poseidon hash on input
-    return input.to_string();
+    input.to_string()
 }
 
 fn add_amount_denom_to_locked_balance(
@@ -97,6 +124,17 @@ fn remove_amount_denom_to_locked_balance(
     Ok(())
 }
 
+fn get_locked_balance(storage: &dyn Storage, addr: Addr, denom: &Denom) -> StdResult<Uint128> {
+    let locked_balances = MAP_ADDR_TO_LOCKED_BALANCES
+        .may_load(storage, addr)?
+        .unwrap_or_default();
+    Ok(locked_balances
+        .iter()
+        .find(|ad| &ad.denom == denom)
+        .map(|ad| ad.amount)
+        .unwrap_or_default())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -106,36 +144,180 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let denom = match msg.denom {
-        DenomUnvalidated::Native(denom) => Denom::Native(denom),
-        DenomUnvalidated::Cw20(addr) => Denom::Cw20(deps.api.addr_validate(&addr)?),
+    // Each tier gets its own commitment tree. Trees only keep their own root
+    // history when roots expire by position
+    let root_expiry = msg.root_expiry.unwrap_or_default();
+    let root_history_size = match root_expiry {
+        RootExpiry::History { size } => size,
+        RootExpiry::Blocks { .. } => 1,
     };
-    DEPOSIT_DENOM.save(deps.storage, &denom)?;
-    DEPOSIT_AMOUNT.save(deps.storage, &msg.amount)?;
+    if root_history_size == 0 {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Root history size must be greater than zero".into(),
+        }));
+    }
+    ROOT_EXPIRY.save(deps.storage, &root_expiry)?;
+    if msg.tiers.is_empty() {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "At least one tier is required".into(),
+        }));
+    }
+    for (tier_id, tier) in msg.tiers.into_iter().enumerate() {
+        let tier_id = tier_id as u32;
+        TIERS.save(
+            deps.storage,
+            tier_id,
+            &Tier {
+                denom: tier.denom.validate(deps.api)?,
+                amount: tier.amount,
+            },
+        )?;
+        // The first epoch of each tier uses the tier id as tree id
+        COMMITMENTS.init(deps.storage, tier_id, TREE_LEVELS, root_history_size)?;
+        EPOCH_TREES.save(deps.storage, (tier_id, 0), &tier_id)?;
+        CURRENT_EPOCHS.save(deps.storage, tier_id, &0)?;
+        NEXT_TREE_ID.save(deps.storage, &(tier_id + 1))?;
+        WITHDRAWAL_COUNTS.save(deps.storage, tier_id, &0)?;
+    }
 
     // Instantiate verifiers
     let deposit_v = Verifier::from_vk(msg.vk_deposit);
     let swap_deposit_v = Verifier::from_vk(msg.vk_swap_deposit);
     let withdraw_v = Verifier::from_vk(msg.vk_withdraw);
+    let reclaim_v = Verifier::from_vk(msg.vk_reclaim);
 
     DEPOSIT_VERIFIER.save(deps.storage, &deposit_v)?;
     MIGRATE_VERIFIER.save(deps.storage, &swap_deposit_v)?;
     WITHDRAW_VERIFIER.save(deps.storage, &withdraw_v)?;
+    RECLAIM_VERIFIER.save(deps.storage, &reclaim_v)?;
+    RECLAIM_DELAY_BLOCKS.save(deps.storage, &msg.reclaim_delay_blocks)?;
+    MIGRATION_POLICY.save(deps.storage, &msg.migration_policy.unwrap_or_default())?;
+
+    // No admin makes allowed pool list immutable
+    if let Some(addr) = msg.pool_admin {
+        let addr = deps.api.addr_validate(&addr)?;
+        POOL_ADMIN.save(deps.storage, &addr)?;
+    }
+    save_allowed_pools(deps.storage, deps.api, msg.allowed_pools)?;
+    save_cw20_wrappers(deps.storage, deps.api, msg.cw20_wrappers)?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // Move the single pool from before deposit tiers into tier 0
+    let mut migrated_trees = 0;
+    if let Some(amount) = LEGACY_DEPOSIT_AMOUNT.may_load(deps.storage)? {
+        // The old keys can't prove tier ids
+        if msg.vk_deposit.is_none() || msg.vk_swap_deposit.is_none() {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Deposit and migrate verifying keys with tier ids are required".into(),
+            }));
+        }
+        let denom = LEGACY_DEPOSIT_DENOM.load(deps.storage)?;
+        TIERS.save(deps.storage, 0, &Tier { denom, amount })?;
+        LEGACY_DEPOSIT_AMOUNT.remove(deps.storage);
+        LEGACY_DEPOSIT_DENOM.remove(deps.storage);
 
-    let tree = MerkleTreeWithHistory::new(20);
-    COMMITMENTS.save(deps.storage, &tree)?;
+        let tree = LEGACY_COMMITMENT_TREE.load(deps.storage)?;
+        COMMITMENTS.import(deps.storage, 0, &tree)?;
+        backfill_root_info(deps.storage, 0, &tree, env.block.height)?;
+        LEGACY_COMMITMENT_TREE.remove(deps.storage);
+        EPOCH_TREES.save(deps.storage, (0, 0), &0)?;
+        migrated_trees += 1;
 
-    match msg.pool_admin {
-        Some(addr) => {
-            let addr = deps.api.addr_validate(&addr)?;
-            POOL_ADMIN.save(deps.storage, &addr)?;
+        // The imported tree keeps the old leaves, Poseidon(wallet, secret), which the
+        // current circuits can't prove. It stays epoch 0 under the old migrate key, and
+        // new deposits go to a fresh tree in epoch 1. Its notes have no credentials on
+        // record, so they can be migrated but not reclaimed
+        let legacy_verifier = MIGRATE_VERIFIER.load(deps.storage)?;
+        LEGACY_MIGRATE_VERIFIER.save(deps.storage, &legacy_verifier)?;
+        let meta = COMMITMENTS.meta(deps.storage, 0)?;
+        COMMITMENTS.init(deps.storage, 1, meta.levels, meta.root_history_size)?;
+        EPOCH_TREES.save(deps.storage, (0, 1), &1)?;
+        CURRENT_EPOCHS.save(deps.storage, 0, &1)?;
+        NEXT_TREE_ID.save(deps.storage, &2)?;
+
+        let nullifiers = LEGACY_NULLIFIERS
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        WITHDRAWAL_COUNTS.save(deps.storage, 0, &(nullifiers.len() as u32))?;
+        for nullifier in nullifiers {
+            NULLIFIERS.save(deps.storage, (0, nullifier.clone()), &true)?;
+            LEGACY_NULLIFIERS.remove(deps.storage, nullifier);
         }
-        // No admin makes allowed pool list immutable
-        None => (),
-    };
-    ALLOWED_POOLS.save(deps.storage, &msg.allowed_pools)?;
 
-    Ok(Response::default())
+        ROOT_EXPIRY.save(deps.storage, &RootExpiry::default())?;
+        MIGRATION_POLICY.save(deps.storage, &MigrationPolicy::default())?;
+    }
+
+    // Pools allowed before venues were all Osmosis pools
+    if let Some(pool_ids) = LEGACY_ALLOWED_POOLS.may_load(deps.storage)? {
+        for pool_id in pool_ids {
+            ALLOWED_POOLS.save(deps.storage, pool_id, &PoolVenue::Osmosis)?;
+        }
+        LEGACY_ALLOWED_POOLS.remove(deps.storage);
+    }
+
+    if let Some(vk) = msg.vk_deposit {
+        DEPOSIT_VERIFIER.save(deps.storage, &Verifier::from_vk(vk))?;
+    }
+    if let Some(vk) = msg.vk_swap_deposit {
+        MIGRATE_VERIFIER.save(deps.storage, &Verifier::from_vk(vk))?;
+    }
+    if let Some(vk) = msg.vk_withdraw {
+        WITHDRAW_VERIFIER.save(deps.storage, &Verifier::from_vk(vk))?;
+    }
+    if let Some(vk) = msg.vk_reclaim {
+        RECLAIM_VERIFIER.save(deps.storage, &Verifier::from_vk(vk))?;
+    }
+    if let Some(reclaim_delay_blocks) = msg.reclaim_delay_blocks {
+        RECLAIM_DELAY_BLOCKS.save(deps.storage, &reclaim_delay_blocks)?;
+    }
+    if RECLAIM_VERIFIER.may_load(deps.storage)?.is_none()
+        || RECLAIM_DELAY_BLOCKS.may_load(deps.storage)?.is_none()
+    {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Reclaim verifying key and delay are required".into(),
+        }));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("migrated_trees", migrated_trees.to_string()))
+}
+
+/// Records when the roots of an imported tree were made, for those that predate
+/// `ROOT_INFO`. Trees stored whole made one root per leaf, so a root `k` slots before
+/// the current one had `k` fewer leaves. The heights are unknown and taken to be the
+/// migration's, which keeps old roots from passing `min_root_age_blocks` early
+fn backfill_root_info(
+    storage: &mut dyn Storage,
+    tier_id: u32,
+    tree: &MerkleTreeWithHistory,
+    height: u64,
+) -> StdResult<()> {
+    let root_history_size = tree.roots.len() as u32;
+    for (slot, root) in (0..).zip(tree.roots.iter()) {
+        let age = (tree.current_root_index + root_history_size - slot) % root_history_size;
+        // Only roots of non-empty trees are recorded
+        let leaves = tree.next_index.saturating_sub(age);
+        let key = (tier_id, root.to_string());
+        if root.is_zero() || leaves == 0 || ROOT_INFO.has(storage, key.clone()) {
+            continue;
+        }
+        ROOT_INFO.save(
+            storage,
+            key,
+            &RootInfo {
+                height,
+                leaves,
+                epoch: 0,
+            },
+        )?;
+    }
+    Ok(())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -147,34 +329,69 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::Deposit {
+            tier_id,
             proof,
             credential,
             withdraw_addr,
         } => {
             let withdraw_addr = deps.api.addr_validate(&withdraw_addr)?;
-            execute_deposit(deps, info, env, proof.to_proof(), credential, withdraw_addr)
+            execute_deposit(
+                deps,
+                info,
+                env,
+                tier_id,
+                proof.to_proof(),
+                credential,
+                withdraw_addr,
+            )
+        }
+        ExecuteMsg::DepositBatch { tier_id, deposits } => {
+            execute_deposit_batch(deps, info, env, tier_id, deposits)
+        }
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender,
+            amount,
+            msg,
+        }) => {
+            let sender = deps.api.addr_validate(&sender)?;
+            match from_binary::<ReceiveMsg>(&msg)? {
+                ReceiveMsg::Deposit(deposit) => {
+                    execute_receive_deposit(deps, info, env, sender, amount, deposit)
+                }
+                ReceiveMsg::DepositBatch { tier_id, deposits } => execute_receive_deposit_batch(
+                    deps, info, env, sender, amount, tier_id, deposits,
+                ),
+            }
         }
         ExecuteMsg::MigrateDeposit {
+            tier_id,
             proof,
             root,
             nullifier_hash,
-        } => execute_migrate_deposit(deps, info, env, proof.to_proof(), root, nullifier_hash),
-        ExecuteMsg::Swap {
-            routes,
-            input_amount,
-            input_denom,
-            min_output_amount,
-            output_denom,
-        } => execute_swap(
+        } => execute_migrate_deposit(
             deps,
             info,
             env,
-            routes,
-            input_amount,
-            input_denom,
-            min_output_amount,
-            output_denom,
+            tier_id,
+            proof.to_proof(),
+            root,
+            nullifier_hash,
+        ),
+        ExecuteMsg::Reclaim {
+            tier_id,
+            proof,
+            credential,
+            nullifier_hash,
+        } => execute_reclaim(
+            deps,
+            info,
+            env,
+            tier_id,
+            proof.to_proof(),
+            credential,
+            nullifier_hash,
         ),
+        ExecuteMsg::Swap(swap) => execute_swap(deps, info, env, swap),
         ExecuteMsg::Withdraw {
             proof,
             withdraw_addr,
@@ -192,6 +409,26 @@ pub fn execute(
             )
         }
         ExecuteMsg::UpdateAllowedPools { pools } => execute_update_allowed_pools(deps, info, pools),
+        ExecuteMsg::UpdateMigrationPolicy { policy } => {
+            execute_update_migration_policy(deps, info, policy)
+        }
+        ExecuteMsg::UpdateCw20Wrappers { wrappers } => {
+            execute_update_cw20_wrappers(deps, info, wrappers)
+        }
+        ExecuteMsg::PlaceLimitOrder(order) => execute_place_limit_order(deps, info, env, order),
+        ExecuteMsg::CancelLimitOrder { order_id } => {
+            execute_cancel_limit_order(deps, info, order_id)
+        }
+        ExecuteMsg::ExecuteOrders { order_ids } => execute_orders(deps, info, env, order_ids),
+        ExecuteMsg::CreateDcaSchedule(schedule) => {
+            execute_create_dca_schedule(deps, info, env, schedule)
+        }
+        ExecuteMsg::CancelDcaSchedule { schedule_id } => {
+            execute_cancel_dca_schedule(deps, info, schedule_id)
+        }
+        ExecuteMsg::TriggerDca { schedule_ids } => {
+            execute_trigger_dca(deps, info, env, schedule_ids)
+        }
     }
 }
 
@@ -199,120 +436,409 @@ pub fn execute_deposit(
     deps: DepsMut,
     info: MessageInfo,
     env: Env,
+    tier_id: u32,
     proof: Proof<Bn254>,
     credential: String,
     withdraw_addr: Addr,
 ) -> Result<Response, ContractError> {
     // 1. Confirm deposit amount and denom
-    let deposit_amount = DEPOSIT_AMOUNT.load(deps.storage)?;
-    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
-    let mut msgs: Vec<CosmosMsg> = vec![];
-    match deposit_denom {
+    let tier = load_tier(deps.storage, tier_id)?;
+    match tier.denom {
+        Denom::Native(denom) => {
+            if info.funds.len() != 1 {
+                return Err(ContractError::InvalidDeposit {});
+            }
+            if info.funds[0].amount != tier.amount {
+                return Err(ContractError::InvalidDeposit {});
+            }
+            if info.funds[0].denom != denom {
+                return Err(ContractError::InvalidDeposit {});
+            }
+        }
+        // CW20 deposits must arrive through `Receive`
+        Denom::Cw20(_) => return Err(ContractError::InvalidDeposit {}),
+    }
+
+    let leaf_events = insert_deposits(
+        deps,
+        &env,
+        tier_id,
+        vec![DepositNote {
+            proof,
+            credential,
+            withdraw_addr,
+        }],
+    )?;
+
+    Ok(Response::default()
+        .add_attribute("action", "deposit")
+        .add_attribute("tier_id", tier_id.to_string())
+        .add_attribute("from", info.sender)
+        .add_events(leaf_events))
+}
+
+pub fn execute_deposit_batch(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    tier_id: u32,
+    deposits: Vec<BatchDeposit>,
+) -> Result<Response, ContractError> {
+    // 1. Confirm the funds cover one tier amount per note
+    let tier = load_tier(deps.storage, tier_id)?;
+    let total = get_batch_amount(&tier, deposits.len())?;
+    match tier.denom {
         Denom::Native(denom) => {
             if info.funds.len() != 1 {
                 return Err(ContractError::InvalidDeposit {});
             }
-            if info.funds[0].amount != deposit_amount {
+            if info.funds[0].amount != total {
                 return Err(ContractError::InvalidDeposit {});
             }
             if info.funds[0].denom != denom {
                 return Err(ContractError::InvalidDeposit {});
             }
         }
+        // CW20 deposits must arrive through `Receive`
+        Denom::Cw20(_) => return Err(ContractError::InvalidDeposit {}),
+    }
+
+    let notes = validate_batch_deposits(deps.api, deposits)?;
+    let leaf_events = insert_deposits(deps, &env, tier_id, notes)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "deposit_batch")
+        .add_attribute("tier_id", tier_id.to_string())
+        .add_attribute("from", info.sender)
+        .add_events(leaf_events))
+}
+
+pub fn execute_receive_deposit(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+    deposit: DepositMsg,
+) -> Result<Response, ContractError> {
+    let DepositMsg {
+        tier_id,
+        proof,
+        credential,
+        withdraw_addr,
+    } = deposit;
+    let withdraw_addr = deps.api.addr_validate(&withdraw_addr)?;
+
+    // 1. Confirm the tokens come from the tier's CW20 contract and match the tier amount
+    let tier = load_tier(deps.storage, tier_id)?;
+    match tier.denom {
         Denom::Cw20(addr) => {
-            msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: addr.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: env.contract.address.to_string(),
-                    amount: deposit_amount,
-                })?,
-                funds: vec![],
-            }));
+            if info.sender != addr {
+                return Err(ContractError::InvalidDeposit {});
+            }
+            if amount != tier.amount {
+                return Err(ContractError::InvalidDeposit {});
+            }
         }
+        Denom::Native(_) => return Err(ContractError::InvalidDeposit {}),
     }
-    // 2. Verify SNARK
+
+    let leaf_events = insert_deposits(
+        deps,
+        &env,
+        tier_id,
+        vec![DepositNote {
+            proof: proof.to_proof(),
+            credential,
+            withdraw_addr,
+        }],
+    )?;
+
+    Ok(Response::default()
+        .add_attribute("action", "deposit")
+        .add_attribute("tier_id", tier_id.to_string())
+        .add_attribute("from", sender)
+        .add_events(leaf_events))
+}
+
+pub fn execute_receive_deposit_batch(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+    tier_id: u32,
+    deposits: Vec<BatchDeposit>,
+) -> Result<Response, ContractError> {
+    // 1. Confirm the tokens come from the tier's CW20 contract and cover one tier amount per note
+    let tier = load_tier(deps.storage, tier_id)?;
+    let total = get_batch_amount(&tier, deposits.len())?;
+    match tier.denom {
+        Denom::Cw20(addr) => {
+            if info.sender != addr {
+                return Err(ContractError::InvalidDeposit {});
+            }
+            if amount != total {
+                return Err(ContractError::InvalidDeposit {});
+            }
+        }
+        Denom::Native(_) => return Err(ContractError::InvalidDeposit {}),
+    }
+
+    let notes = validate_batch_deposits(deps.api, deposits)?;
+    let leaf_events = insert_deposits(deps, &env, tier_id, notes)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "deposit_batch")
+        .add_attribute("tier_id", tier_id.to_string())
+        .add_attribute("from", sender)
+        .add_events(leaf_events))
+}
+
+fn load_tier(storage: &dyn Storage, tier_id: u32) -> Result<Tier, ContractError> {
+    TIERS
+        .may_load(storage, tier_id)?
+        .ok_or(ContractError::UnknownTier { id: tier_id })
+}
+
+// Spent notes are counted so the tier's value is known without its balance,
+// which also holds locked balances and swap outputs
+fn spend_nullifier(storage: &mut dyn Storage, tier_id: u32, nullifier: String) -> StdResult<()> {
+    NULLIFIERS.save(storage, (tier_id, nullifier), &true)?;
+    let withdrawal_count = WITHDRAWAL_COUNTS.load(storage, tier_id)?;
+    WITHDRAWAL_COUNTS.save(storage, tier_id, &(withdrawal_count + 1))
+}
+
+// A deposit whose proof is yet to be verified
+struct DepositNote {
+    proof: Proof<Bn254>,
+    credential: String,
+    withdraw_addr: Addr,
+}
+
+fn get_batch_amount(tier: &Tier, count: usize) -> Result<Uint128, ContractError> {
+    if count == 0 {
+        return Err(ContractError::InvalidDeposit {});
+    }
+    tier.amount
+        .checked_mul(Uint128::from(count as u128))
+        .map_err(|_| ContractError::InvalidDeposit {})
+}
+
+fn validate_batch_deposits(
+    api: &dyn Api,
+    deposits: Vec<BatchDeposit>,
+) -> StdResult<Vec<DepositNote>> {
+    deposits
+        .into_iter()
+        .map(|deposit| {
+            Ok(DepositNote {
+                proof: deposit.proof.to_proof(),
+                credential: deposit.credential,
+                withdraw_addr: api.addr_validate(&deposit.withdraw_addr)?,
+            })
+        })
+        .collect()
+}
+
+/// Verifies the deposit proofs and inserts their credentials into the tier's commitment
+/// tree, returning the leaf index of each. The tier id is a public input, so a credential
+/// only proves a deposit into its own tier
+fn insert_deposits(
+    deps: DepsMut,
+    env: &Env,
+    tier_id: u32,
+    notes: Vec<DepositNote>,
+) -> Result<Vec<Event>, ContractError> {
+    // 2. Verify SNARKs, preparing the verifying key once for the whole batch
     let verifier = DEPOSIT_VERIFIER.load(deps.storage)?;
-    let public_signals = PublicSignals(vec![
-        poseidon_hash(&withdraw_addr.to_string()).to_string(),
-        credential.clone(),
-    ]);
-    let success = verifier.verify_proof(proof, &public_signals.get());
+    let proofs = notes
+        .iter()
+        .map(|note| {
+            let public_signals = PublicSignals(vec![
+                poseidon_hash(note.withdraw_addr.as_str()),
+                note.credential.clone(),
+                tier_id.to_string(),
+            ]);
+            (note.proof.clone(), public_signals.get())
+        })
+        .collect();
+    let success = verifier.verify_proofs(proofs);
     if !success {
         return Err(ContractError::InvalidProof {});
     }
 
-    // 3. Insert commitment into merkle tree
-    let mut commitment_mt = COMMITMENTS.load(deps.storage)?;
-    // confirm insert worked
-    let success = commitment_mt.insert(&Uint256::from_str(&credential)?);
-    if success.is_none() {
-        return Err(ContractError::InvalidCommitment {});
+    // 3. Insert commitments into merkle tree in one batch, so a batch deposit records a
+    // single root. Each leaf gets a `commitment` event, from which off-chain trees
+    // (`lib::merkle_tree::FullMerkleTree`) can be rebuilt
+    let (epoch, tree_id) = get_deposit_tree(deps.storage, tier_id, notes.len() as u32)?;
+    let first_index = COMMITMENTS.meta(deps.storage, tree_id)?.next_index;
+    let mut leaves = vec![];
+    let mut leaf_events = vec![];
+    for (leaf_index, note) in (first_index..).zip(notes) {
+        // Each credential may only be deposited once, so leaves are unambiguous
+        let key = (tier_id, note.credential.clone());
+        if CREDENTIALS.has(deps.storage, key.clone()) {
+            return Err(ContractError::InvalidDepositCredential {});
+        }
+
+        leaves.push(Uint256::from_str(&note.credential)?);
+        CREDENTIALS.save(
+            deps.storage,
+            key,
+            &DepositInfo {
+                epoch,
+                leaf_index,
+                height: env.block.height,
+                withdraw_addr: note.withdraw_addr,
+            },
+        )?;
+        leaf_events.push(
+            Event::new("commitment")
+                .add_attribute("tier_id", tier_id.to_string())
+                .add_attribute("epoch", epoch.to_string())
+                .add_attribute("leaf_index", leaf_index.to_string())
+                .add_attribute("commitment", note.credential),
+        );
     }
 
-    COMMITMENTS.save(deps.storage, &commitment_mt)?;
+    // confirm insert worked
+    COMMITMENTS
+        .insert_batch(deps.storage, tree_id, &leaves)
+        .map_err(|_| ContractError::InvalidCommitment {})?;
+    let root = COMMITMENTS.get_last_root(deps.storage, tree_id)?;
+    ROOT_INFO.save(
+        deps.storage,
+        (tier_id, root.to_string()),
+        &RootInfo {
+            height: env.block.height,
+            leaves: first_index + leaves.len() as u32,
+            epoch,
+        },
+    )?;
+
+    Ok(leaf_events)
+}
 
-    Ok(Response::default()
-        .add_messages(msgs)
-        .add_attribute("action", "deposit")
-        .add_attribute("from", info.sender))
+/// Epoch and tree id that take the next `count` deposits of a tier. Starts a new epoch
+/// when the current tree can't fit them; the filled tree is left as is, so its last
+/// roots stay valid for migrations
+fn get_deposit_tree(storage: &mut dyn Storage, tier_id: u32, count: u32) -> StdResult<(u32, u32)> {
+    let epoch = CURRENT_EPOCHS.load(storage, tier_id)?;
+    let tree_id = EPOCH_TREES.load(storage, (tier_id, epoch))?;
+    let meta = COMMITMENTS.meta(storage, tree_id)?;
+    if meta.next_index as u64 + count as u64 <= 2_u64.pow(meta.levels) {
+        return Ok((epoch, tree_id));
+    }
+
+    let next_epoch = epoch + 1;
+    let next_tree_id = NEXT_TREE_ID.load(storage)?;
+    COMMITMENTS.init(storage, next_tree_id, meta.levels, meta.root_history_size)?;
+    NEXT_TREE_ID.save(storage, &(next_tree_id + 1))?;
+    EPOCH_TREES.save(storage, (tier_id, next_epoch), &next_tree_id)?;
+    CURRENT_EPOCHS.save(storage, tier_id, &next_epoch)?;
+    Ok((next_epoch, next_tree_id))
 }
 
 pub fn execute_migrate_deposit(
     deps: DepsMut,
     info: MessageInfo,
-    _env: Env,
+    env: Env,
+    tier_id: u32,
     proof: Proof<Bn254>,
     root: String,
     nullifier: String,
 ) -> Result<Response, ContractError> {
-    // 1. Reject if nullifier hash is in map
-    match NULLIFIERS.may_load(deps.storage, nullifier.clone())? {
-        Some(_) => return Err(ContractError::DuplicateCommitment {}),
-        None => (),
-    };
+    let tier = load_tier(deps.storage, tier_id)?;
+
+    // 1. Reject if nullifier hash is in the tier's map
+    if NULLIFIERS.has(deps.storage, (tier_id, nullifier.clone())) {
+        return Err(ContractError::DuplicateCommitment {});
+    }
 
     // 2. Verify SNARK
 
-    // Confirm root known
-    let commitment_mt = COMMITMENTS.load(deps.storage)?;
-    assert_ne!(
-        commitment_mt.current_root_index, 0,
-        "Commitment merkle tree shouldn't be 0"
-    );
-    if !commitment_mt.is_known_root(&Uint256::from_str(&root).unwrap()) {
+    // Confirm root known. Only roots of non-empty trees are recorded
+    let root_value = Uint256::from_str(&root)?;
+    let root_info = ROOT_INFO
+        .may_load(deps.storage, (tier_id, root_value.to_string()))?
+        .ok_or(ContractError::UnknownRoot {})?;
+    let tree_id = EPOCH_TREES.load(deps.storage, (tier_id, root_info.epoch))?;
+    // The last root of a filled epoch is never replaced, so it doesn't expire
+    let final_root = root_info.epoch < CURRENT_EPOCHS.load(deps.storage, tier_id)?
+        && COMMITMENTS.get_last_root(deps.storage, tree_id)? == root_value;
+    let known_root = final_root
+        || match ROOT_EXPIRY.load(deps.storage)? {
+            RootExpiry::History { .. } => {
+                COMMITMENTS.is_known_root(deps.storage, tree_id, &root_value)?
+            }
+            RootExpiry::Blocks { blocks } => {
+                env.block.height <= root_info.height.saturating_add(blocks)
+            }
+        };
+    if !known_root {
         return Err(ContractError::UnknownRoot {});
     }
 
+    // Enforce the migration policy on the referenced root
+    let policy = MIGRATION_POLICY.load(deps.storage)?;
+    if let Some(min_root_age_blocks) = policy.min_root_age_blocks {
+        if env.block.height < root_info.height.saturating_add(min_root_age_blocks) {
+            return Err(ContractError::RootTooRecent {});
+        }
+    }
+    if let Some(min_anonymity_set) = policy.min_anonymity_set {
+        if root_info.leaves < min_anonymity_set {
+            return Err(ContractError::AnonymitySetTooSmall {});
+        }
+    }
+
     // Get previous nullifier. If none, set to 0
     let previous_nullifier = MAP_ADDR_TO_PREVIOUS_NULLIFIER
         .may_load(deps.storage, info.sender.clone())?
         .unwrap_or(Uint256::zero());
 
-    let verifier = MIGRATE_VERIFIER.load(deps.storage)?;
-    let public_signals = PublicSignals(vec![
-        root.clone(),
-        nullifier.clone(),
-        previous_nullifier.to_string(),
-    ]);
+    // Notes of the single pool from before deposit tiers are proven without a tier id
+    let legacy_verifier = match (tier_id, root_info.epoch) {
+        (0, 0) => LEGACY_MIGRATE_VERIFIER.may_load(deps.storage)?,
+        _ => None,
+    };
+    let (verifier, public_signals) = match legacy_verifier {
+        Some(verifier) => (
+            verifier,
+            PublicSignals(vec![
+                root.clone(),
+                nullifier.clone(),
+                previous_nullifier.to_string(),
+            ]),
+        ),
+        None => (
+            MIGRATE_VERIFIER.load(deps.storage)?,
+            PublicSignals(vec![
+                root.clone(),
+                nullifier.clone(),
+                previous_nullifier.to_string(),
+                tier_id.to_string(),
+            ]),
+        ),
+    };
     let success = verifier.verify_proof(proof, &public_signals.get());
     if !success {
         return Err(ContractError::InvalidProof {});
     }
 
-    // 3. Give amount, denom to user.
-    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
-    let deposit_amount = DEPOSIT_AMOUNT.load(deps.storage)?;
+    // 3. Give the tier's amount, denom to user.
     add_amount_denom_to_locked_balance(
         deps.storage,
         info.sender.clone(),
         AmountDenom {
-            amount: deposit_amount,
-            denom: deposit_denom.clone(),
+            amount: tier.amount,
+            denom: tier.denom,
         },
     )?;
 
-    // 4. Insert nullifier hash into map
-    NULLIFIERS.save(deps.storage, nullifier.clone(), &true)?;
+    // 4. Insert nullifier hash into the tier's map
+    spend_nullifier(deps.storage, tier_id, nullifier.clone())?;
 
     // 5. Set nullifier to previous nullifier
     MAP_ADDR_TO_PREVIOUS_NULLIFIER.save(
@@ -323,27 +849,78 @@ pub fn execute_migrate_deposit(
 
     Ok(Response::new()
         .add_attribute("action", "migrate_deposit")
+        .add_attribute("tier_id", tier_id.to_string())
         .add_attribute("from", info.sender.clone()))
 }
 
+pub fn execute_reclaim(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    tier_id: u32,
+    proof: Proof<Bn254>,
+    credential: String,
+    nullifier: String,
+) -> Result<Response, ContractError> {
+    let tier = load_tier(deps.storage, tier_id)?;
+
+    // 1. Reject if nullifier hash is in the tier's map, i.e. the deposit was migrated
+    if NULLIFIERS.has(deps.storage, (tier_id, nullifier.clone())) {
+        return Err(ContractError::DuplicateCommitment {});
+    }
+
+    // 2. Only deposits older than the reclaim delay
+    let deposit = CREDENTIALS
+        .may_load(deps.storage, (tier_id, credential.clone()))?
+        .ok_or(ContractError::InvalidDepositCredential {})?;
+    let reclaim_delay_blocks = RECLAIM_DELAY_BLOCKS.load(deps.storage)?;
+    if env.block.height < deposit.height.saturating_add(reclaim_delay_blocks) {
+        return Err(ContractError::ReclaimTooEarly {});
+    }
+
+    // 3. Verify SNARK
+    let verifier = RECLAIM_VERIFIER.load(deps.storage)?;
+    let public_signals = PublicSignals(vec![
+        poseidon_hash(deposit.withdraw_addr.as_str()),
+        credential,
+        nullifier.clone(),
+        tier_id.to_string(),
+    ]);
+    let success = verifier.verify_proof(proof, &public_signals.get());
+    if !success {
+        return Err(ContractError::InvalidProof {});
+    }
+
+    // 4. Insert nullifier hash into the tier's map
+    spend_nullifier(deps.storage, tier_id, nullifier)?;
+
+    // 5. Send the deposit back to the withdraw address
+    let msg = get_send_msg(&tier.denom, &deposit.withdraw_addr, tier.amount)?;
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("action", "reclaim")
+        .add_attribute("tier_id", tier_id.to_string())
+        .add_attribute("leaf_index", deposit.leaf_index.to_string())
+        .add_attribute("from", info.sender))
+}
+
 pub fn execute_swap(
     deps: DepsMut,
     info: MessageInfo,
     env: Env,
-    routes: Vec<OsmosisRoute>,
-    input_amount: Uint128,
-    input_denom: DenomUnvalidated,
-    min_output_amount: Uint128,
-    output_denom: DenomUnvalidated,
+    swap: SwapMsg,
 ) -> Result<Response, ContractError> {
-    let input_denom_validated = match input_denom {
-        DenomUnvalidated::Native(denom) => Denom::Native(denom),
-        DenomUnvalidated::Cw20(addr) => Denom::Cw20(deps.api.addr_validate(&addr)?),
-    };
-    let output_denom_validated = match output_denom {
-        DenomUnvalidated::Native(denom) => Denom::Native(denom),
-        DenomUnvalidated::Cw20(addr) => Denom::Cw20(deps.api.addr_validate(&addr)?),
-    };
+    let SwapMsg {
+        routes,
+        input_amount,
+        input_denom,
+        min_output_amount,
+        output_denom,
+        max_slippage_bps,
+    } = swap;
+    let input_denom_validated = input_denom.validate(deps.api)?;
+    let output_denom_validated = output_denom.validate(deps.api)?;
 
     // Try to remove amount from locked balance
     remove_amount_denom_to_locked_balance(
@@ -355,35 +932,45 @@ pub fn execute_swap(
         },
     )?;
 
-    // Add swap message with reply handler
-    let allowed_pool_ids = ALLOWED_POOLS.load(deps.storage)?;
-    let msg = get_osmosis_swap_msg(
-        allowed_pool_ids,
-        env.contract.address.clone(),
-        routes,
-        input_amount,
-        input_denom_validated.clone(),
-        min_output_amount,
-        output_denom_validated.clone(),
-    )?;
-    let sub_msg = SubMsg::reply_on_success(msg, SWAP_REPLY_ID);
-    let output_balance_before_swap = get_denom_balance(
-        deps.as_ref(),
-        output_denom_validated.clone(),
-        env.contract.address,
-    )?;
-    SWAP_CTX.save(
+    // Use the stricter of the user's minimum and the TWAP-derived floor
+    let min_output_amount = match max_slippage_bps {
+        Some(bps) => {
+            let twap_min_output = get_twap_min_output(
+                deps.as_ref(),
+                &env,
+                &routes,
+                input_amount,
+                &input_denom_validated,
+                bps,
+            )?;
+            std::cmp::max(min_output_amount, twap_min_output)
+        }
+        None => min_output_amount,
+    };
+
+    // Add swap messages with reply handler
+    let swap = get_swap_msgs(
         deps.storage,
-        &SwapContext {
-            // Save the output of the swap into the contract state, so we can use it when handling the reply from the swap
-            swapper_addr: info.sender.clone(),
-            output_balance_before_swap,
-            output_denom: output_denom_validated.clone(),
+        &env.contract.address,
+        &routes,
+        &AmountDenom {
+            amount: input_amount,
+            denom: input_denom_validated,
         },
+        min_output_amount,
+        &output_denom_validated,
+    )?;
+    let sub_msgs = queue_swap(
+        deps,
+        &env,
+        swap,
+        info.sender.clone(),
+        min_output_amount,
+        None,
     )?;
 
     Ok(Response::new()
-        .add_submessage(sub_msg)
+        .add_submessages(sub_msgs)
         .add_attribute("action", "swap")
         .add_attribute("from", info.sender))
 }
@@ -405,7 +992,7 @@ pub fn execute_withdraw(
 
     let verifier = WITHDRAW_VERIFIER.load(deps.storage)?;
     let public_signals = PublicSignals(vec![
-        poseidon_hash(&withdraw_addr.to_string()).to_string(),
+        poseidon_hash(withdraw_addr.as_str()),
         previous_nullifier.to_string(),
     ]);
     let success = verifier.verify_proof(proof, &public_signals.get());
@@ -423,27 +1010,7 @@ pub fn execute_withdraw(
     // Send all locked balances to withdraw address
     let mut msgs: Vec<CosmosMsg> = vec![];
     for ad in locked_balances.iter() {
-        match &ad.denom {
-            Denom::Native(denom) => {
-                msgs.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: withdraw_addr.to_string(),
-                    amount: vec![Coin {
-                        amount: ad.amount,
-                        denom: denom.to_string(),
-                    }],
-                }));
-            }
-            Denom::Cw20(addr) => {
-                msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: addr.to_string(),
-                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                        recipient: withdraw_addr.to_string(),
-                        amount: ad.amount,
-                    })?,
-                    funds: vec![],
-                }));
-            }
-        }
+        msgs.push(get_send_msg(&ad.denom, &withdraw_addr, ad.amount)?);
     }
 
     Ok(Response::default()
@@ -454,86 +1021,745 @@ pub fn execute_withdraw(
         .add_attribute("burner", burner_addr))
 }
 
+pub fn execute_place_limit_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    order: LimitOrderMsg,
+) -> Result<Response, ContractError> {
+    let LimitOrderMsg {
+        routes,
+        input_amount,
+        input_denom,
+        output_denom,
+        limit_price,
+        expires_at_height,
+        keeper_tip_bps,
+    } = order;
+    let keeper_tip_bps = keeper_tip_bps.unwrap_or_default();
+    let input_denom = input_denom.validate(deps.api)?;
+    let output_denom = output_denom.validate(deps.api)?;
+    if keeper_tip_bps > MAX_KEEPER_TIP_BPS {
+        return Err(ContractError::InvalidKeeperTip {});
+    }
+    if expires_at_height <= env.block.height {
+        return Err(ContractError::OrderExpired {});
+    }
+
+    // Escrow the input
+    let input = AmountDenom {
+        amount: input_amount,
+        denom: input_denom,
+    };
+
+    // Check the route now rather than when a keeper tries to fill it
+    get_swap_msgs(
+        deps.storage,
+        &env.contract.address,
+        &routes,
+        &input,
+        Uint128::zero(),
+        &output_denom,
+    )?;
+    remove_amount_denom_to_locked_balance(deps.storage, info.sender.clone(), input.clone())?;
+
+    let order_id = NEXT_LIMIT_ORDER_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_LIMIT_ORDER_ID.save(deps.storage, &(order_id + 1))?;
+    LIMIT_ORDERS.save(
+        deps.storage,
+        order_id,
+        &LimitOrder {
+            owner: info.sender.clone(),
+            routes,
+            input,
+            output_denom,
+            limit_price,
+            expires_at_height,
+            keeper_tip_bps,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "place_limit_order")
+        .add_attribute("from", info.sender)
+        .add_attribute("order_id", order_id.to_string()))
+}
+
+pub fn execute_cancel_limit_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    order_id: u64,
+) -> Result<Response, ContractError> {
+    let order = LIMIT_ORDERS
+        .may_load(deps.storage, order_id)?
+        .ok_or(ContractError::UnknownOrder { id: order_id })?;
+    if info.sender != order.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    LIMIT_ORDERS.remove(deps.storage, order_id);
+
+    // Return the escrow to the locked balance
+    add_amount_denom_to_locked_balance(deps.storage, order.owner, order.input)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_limit_order")
+        .add_attribute("from", info.sender)
+        .add_attribute("order_id", order_id.to_string()))
+}
+
+pub fn execute_orders(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    order_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    // Orders that can't be filled right now are skipped, so one stale order
+    // doesn't fail the keeper's whole batch. The estimate can still miss: an order
+    // whose swap then falls short of its limit reverts the batch, filled orders included
+    let mut sub_msgs: Vec<SubMsg> = vec![];
+    let mut filled: Vec<String> = vec![];
+    for order_id in order_ids {
+        let order = match LIMIT_ORDERS.may_load(deps.storage, order_id)? {
+            Some(order) => order,
+            None => continue,
+        };
+        if order.expires_at_height <= env.block.height {
+            continue;
+        }
+        // The keeper's tip comes out of the output, so the limit must hold net of it.
+        // A limit no output amount can meet is never filled
+        let min_output_amount = match Uint256::from(order.input.amount)
+            .checked_multiply_ratio(order.limit_price.atomics(), Decimal256::one().atomics())
+            .ok()
+            .and_then(|amount| Uint128::try_from(amount).ok())
+            .and_then(|amount| add_keeper_tip(amount, order.keeper_tip_bps))
+        {
+            Some(amount) => amount,
+            None => continue,
+        };
+        let estimated_output = match simulate_swap(
+            deps.as_ref(),
+            &order.routes,
+            &order.input,
+            &order.output_denom,
+        ) {
+            Ok(simulation) => simulation.output_amount,
+            Err(_) => continue,
+        };
+        if estimated_output < min_output_amount {
+            continue;
+        }
+
+        // The limit is enforced again when the swap executes
+        let swap = match get_swap_msgs(
+            deps.storage,
+            &env.contract.address,
+            &order.routes,
+            &order.input,
+            min_output_amount,
+            &order.output_denom,
+        ) {
+            Ok(swap) => swap,
+            Err(_) => continue,
+        };
+        let keeper_tip = match order.keeper_tip_bps {
+            0 => None,
+            bps => Some(KeeperTip {
+                keeper: info.sender.clone(),
+                bps,
+            }),
+        };
+        sub_msgs.extend(queue_swap(
+            deps.branch(),
+            &env,
+            swap,
+            order.owner,
+            min_output_amount,
+            keeper_tip,
+        )?);
+
+        LIMIT_ORDERS.remove(deps.storage, order_id);
+        filled.push(order_id.to_string());
+    }
+
+    Ok(Response::new()
+        .add_submessages(sub_msgs)
+        .add_attribute("action", "execute_orders")
+        .add_attribute("from", info.sender)
+        .add_attribute("filled", filled.join(",")))
+}
+
+/// Output of a swap that leaves at least `amount` once a tip of `keeper_tip_bps` is paid
+fn add_keeper_tip(amount: Uint128, keeper_tip_bps: u64) -> Option<Uint128> {
+    let net_bps = Uint256::from(MAX_BPS.checked_sub(keeper_tip_bps)?);
+    if net_bps.is_zero() {
+        return None;
+    }
+    // Rounded up, as the tip is rounded down
+    let gross =
+        (Uint256::from(amount) * Uint256::from(MAX_BPS) + net_bps - Uint256::one()) / net_bps;
+    Uint128::try_from(gross).ok()
+}
+
+/// Current block height or time, in the unit of `interval`
+fn dca_clock(interval: &DcaInterval, env: &Env) -> u64 {
+    match interval {
+        DcaInterval::Blocks(_) => env.block.height,
+        DcaInterval::Seconds(_) => env.block.time.seconds(),
+    }
+}
+
+pub fn execute_create_dca_schedule(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    schedule: DcaScheduleMsg,
+) -> Result<Response, ContractError> {
+    let DcaScheduleMsg {
+        routes,
+        input_denom,
+        output_denom,
+        amount_per_slice,
+        num_slices,
+        interval,
+        max_slippage_bps,
+    } = schedule;
+    let input_denom = input_denom.validate(deps.api)?;
+    let output_denom = output_denom.validate(deps.api)?;
+    let interval_length = match interval {
+        DcaInterval::Blocks(n) | DcaInterval::Seconds(n) => n,
+    };
+    if amount_per_slice.is_zero() || num_slices == 0 || interval_length == 0 {
+        return Err(ContractError::InvalidDcaSchedule {});
+    }
+    if max_slippage_bps > MAX_BPS {
+        return Err(ContractError::InvalidSlippage {});
+    }
+
+    // Check the route now rather than when the first slice is triggered
+    get_swap_msgs(
+        deps.storage,
+        &env.contract.address,
+        &routes,
+        &AmountDenom {
+            amount: amount_per_slice,
+            denom: input_denom.clone(),
+        },
+        Uint128::zero(),
+        &output_denom,
+    )?;
+
+    // Escrow every slice up front
+    let total = amount_per_slice
+        .checked_mul(Uint128::from(num_slices))
+        .map_err(StdError::from)?;
+    remove_amount_denom_to_locked_balance(
+        deps.storage,
+        info.sender.clone(),
+        AmountDenom {
+            amount: total,
+            denom: input_denom.clone(),
+        },
+    )?;
+
+    let schedule_id = NEXT_DCA_SCHEDULE_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_DCA_SCHEDULE_ID.save(deps.storage, &(schedule_id + 1))?;
+    let next_slice_at = dca_clock(&interval, &env);
+    DCA_SCHEDULES.save(
+        deps.storage,
+        schedule_id,
+        &DcaSchedule {
+            owner: info.sender.clone(),
+            routes,
+            input_denom,
+            output_denom,
+            amount_per_slice,
+            remaining: total,
+            interval,
+            next_slice_at,
+            max_slippage_bps,
+        },
+    )?;
+    DCA_SCHEDULES_BY_OWNER.save(deps.storage, (info.sender.clone(), schedule_id), &())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "create_dca_schedule")
+        .add_attribute("from", info.sender)
+        .add_attribute("schedule_id", schedule_id.to_string()))
+}
+
+pub fn execute_cancel_dca_schedule(
+    deps: DepsMut,
+    info: MessageInfo,
+    schedule_id: u64,
+) -> Result<Response, ContractError> {
+    let schedule = DCA_SCHEDULES
+        .may_load(deps.storage, schedule_id)?
+        .ok_or(ContractError::UnknownDcaSchedule { id: schedule_id })?;
+    if info.sender != schedule.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    DCA_SCHEDULES.remove(deps.storage, schedule_id);
+    DCA_SCHEDULES_BY_OWNER.remove(deps.storage, (schedule.owner.clone(), schedule_id));
+
+    // Return the unspent escrow to the locked balance
+    if !schedule.remaining.is_zero() {
+        add_amount_denom_to_locked_balance(
+            deps.storage,
+            schedule.owner,
+            AmountDenom {
+                amount: schedule.remaining,
+                denom: schedule.input_denom,
+            },
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_dca_schedule")
+        .add_attribute("from", info.sender)
+        .add_attribute("schedule_id", schedule_id.to_string()))
+}
+
+pub fn execute_trigger_dca(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    schedule_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    // Schedules that aren't due or can't be priced right now are skipped. A slice
+    // that misses its minimum output when it swaps reverts the whole batch, due
+    // slices included, so keepers should leave out schedules that fail on their own
+    let mut sub_msgs: Vec<SubMsg> = vec![];
+    let mut triggered: Vec<String> = vec![];
+    for schedule_id in schedule_ids {
+        let mut schedule = match DCA_SCHEDULES.may_load(deps.storage, schedule_id)? {
+            Some(schedule) => schedule,
+            None => continue,
+        };
+        let now = dca_clock(&schedule.interval, &env);
+        if now < schedule.next_slice_at {
+            continue;
+        }
+
+        let slice = std::cmp::min(schedule.amount_per_slice, schedule.remaining);
+        let min_output_amount = match get_twap_min_output(
+            deps.as_ref(),
+            &env,
+            &schedule.routes,
+            slice,
+            &schedule.input_denom,
+            schedule.max_slippage_bps,
+        ) {
+            Ok(amount) => amount,
+            Err(_) => continue,
+        };
+        let swap = match get_swap_msgs(
+            deps.storage,
+            &env.contract.address,
+            &schedule.routes,
+            &AmountDenom {
+                amount: slice,
+                denom: schedule.input_denom.clone(),
+            },
+            min_output_amount,
+            &schedule.output_denom,
+        ) {
+            Ok(swap) => swap,
+            Err(_) => continue,
+        };
+        sub_msgs.extend(queue_swap(
+            deps.branch(),
+            &env,
+            swap,
+            schedule.owner.clone(),
+            min_output_amount,
+            None,
+        )?);
+
+        schedule.remaining -= slice;
+        if schedule.remaining.is_zero() {
+            DCA_SCHEDULES.remove(deps.storage, schedule_id);
+            DCA_SCHEDULES_BY_OWNER.remove(deps.storage, (schedule.owner, schedule_id));
+        } else {
+            let interval_length = match schedule.interval {
+                DcaInterval::Blocks(n) | DcaInterval::Seconds(n) => n,
+            };
+            schedule.next_slice_at = now + interval_length;
+            DCA_SCHEDULES.save(deps.storage, schedule_id, &schedule)?;
+        }
+        triggered.push(schedule_id.to_string());
+    }
+
+    Ok(Response::new()
+        .add_submessages(sub_msgs)
+        .add_attribute("action", "trigger_dca")
+        .add_attribute("from", info.sender)
+        .add_attribute("triggered", triggered.join(",")))
+}
+
 pub fn execute_update_allowed_pools(
     deps: DepsMut,
     info: MessageInfo,
-    pools: Vec<String>,
+    pools: Vec<AllowedPool>,
 ) -> Result<Response, ContractError> {
     // No admin makes allowed pool list immutable
     let admin = POOL_ADMIN.load(deps.storage)?;
     if info.sender != admin {
         return Err(ContractError::Unauthorized {});
     }
-    ALLOWED_POOLS.save(deps.storage, &pools)?;
+    save_allowed_pools(deps.storage, deps.api, pools)?;
 
     Ok(Response::default()
         .add_attribute("action", "update_allowed_pools")
         .add_attribute("from", info.sender))
 }
 
-pub fn get_osmosis_swap_msg(
-    allowed_pool_ids: Vec<String>,
-    contract_addr: Addr,
-    routes: Vec<OsmosisRoute>,
-    input_amount: Uint128,
-    input_denom: Denom,
+pub fn execute_update_migration_policy(
+    deps: DepsMut,
+    info: MessageInfo,
+    policy: MigrationPolicy,
+) -> Result<Response, ContractError> {
+    let admin = POOL_ADMIN.load(deps.storage)?;
+    if info.sender != admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    MIGRATION_POLICY.save(deps.storage, &policy)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "update_migration_policy")
+        .add_attribute("from", info.sender))
+}
+
+pub fn execute_update_cw20_wrappers(
+    deps: DepsMut,
+    info: MessageInfo,
+    wrappers: Vec<Cw20WrapperMsg>,
+) -> Result<Response, ContractError> {
+    let admin = POOL_ADMIN.load(deps.storage)?;
+    if info.sender != admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    save_cw20_wrappers(deps.storage, deps.api, wrappers)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "update_cw20_wrappers")
+        .add_attribute("from", info.sender))
+}
+
+/// Wraps a swap's messages in submessages, the last of which replies to credit
+/// the output to `swapper_addr`.
+fn queue_swap(
+    deps: DepsMut,
+    env: &Env,
+    swap: PreparedSwap,
+    swapper_addr: Addr,
+    min_output_amount: Uint128,
+    keeper_tip: Option<KeeperTip>,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let PreparedSwap {
+        msgs,
+        output_denom,
+        unwrap,
+    } = swap;
+
+    // Save the output balance into the contract state, so we can use it when handling the reply from the swap
+    let output_balance_before_swap = get_denom_balance(
+        deps.as_ref(),
+        output_denom.clone(),
+        env.contract.address.clone(),
+    )?;
+    let mut queue = SWAP_QUEUE.may_load(deps.storage)?.unwrap_or_default();
+    queue.push(SwapContext {
+        swapper_addr,
+        output_balance_before_swap,
+        output_denom,
+        min_output_amount,
+        keeper_tip,
+        unwrap,
+    });
+    SWAP_QUEUE.save(deps.storage, &queue)?;
+
+    let last = msgs.len().saturating_sub(1);
+    Ok(msgs
+        .into_iter()
+        .enumerate()
+        .map(|(i, msg)| {
+            if i == last {
+                SubMsg::reply_on_success(msg, SWAP_REPLY_ID)
+            } else {
+                SubMsg::new(msg)
+            }
+        })
+        .collect())
+}
+
+fn save_allowed_pools(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    pools: Vec<AllowedPool>,
+) -> StdResult<()> {
+    let pool_ids = ALLOWED_POOLS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for pool_id in pool_ids {
+        ALLOWED_POOLS.remove(storage, pool_id);
+    }
+    for pool in pools {
+        ALLOWED_POOLS.save(storage, pool.pool_id, &pool.venue.validate(api)?)?;
+    }
+    Ok(())
+}
+
+fn save_cw20_wrappers(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    wrappers: Vec<Cw20WrapperMsg>,
+) -> StdResult<()> {
+    let cw20_addrs = CW20_WRAPPERS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for cw20_addr in cw20_addrs {
+        CW20_WRAPPERS.remove(storage, cw20_addr);
+    }
+    for wrapper in wrappers {
+        CW20_WRAPPERS.save(
+            storage,
+            api.addr_validate(&wrapper.cw20_addr)?,
+            &Cw20Wrapper {
+                converter: api.addr_validate(&wrapper.converter)?,
+                native_denom: wrapper.native_denom,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks every hop of `routes` is through an allowed pool on one venue and
+/// ends in `output_denom`, and returns that venue.
+pub fn validate_routes(
+    storage: &dyn Storage,
+    routes: &[OsmosisRoute],
+    output_denom: &Denom,
+) -> Result<PoolVenue, ContractError> {
+    let venue = get_routes_venue(storage, routes)?;
+    // Quite important to check that the output denom is the last element
+    if routes.last().map(|route| route.token_out_denom.as_str())
+        != Some(route_denom(output_denom).as_str())
+    {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Output denom must be last element of routes".into(),
+        }));
+    }
+    Ok(venue)
+}
+
+/// Venue every hop of `routes` goes through, failing on pools that aren't allowed.
+fn get_routes_venue(
+    storage: &dyn Storage,
+    routes: &[OsmosisRoute],
+) -> Result<PoolVenue, ContractError> {
+    let mut venue: Option<PoolVenue> = None;
+    for route in routes.iter() {
+        let pool_venue = ALLOWED_POOLS
+            .may_load(storage, route.pool_id.clone())?
+            .ok_or(ContractError::InvalidPoolId {
+                id: route.pool_id.clone(),
+            })?;
+        if let Some(venue) = &venue {
+            if venue != &pool_venue {
+                return Err(ContractError::MixedVenues {});
+            }
+        }
+        venue = Some(pool_venue);
+    }
+    venue.ok_or(ContractError::Std(StdError::GenericErr {
+        msg: "Routes must not be empty".into(),
+    }))
+}
+
+/// Messages for a swap, and how its reply finds the output.
+pub struct PreparedSwap {
+    pub msgs: Vec<CosmosMsg>,
+    // Denom the swap itself outputs, which the reply reads the balance of
+    pub output_denom: Denom,
+    pub unwrap: Option<Cw20Unwrap>,
+}
+
+/// Denom a swap on `venue` trades in place of `denom`. Osmosis pools only hold
+/// native denoms, so CW20s go through their configured wrapper.
+fn get_venue_denom(
+    storage: &dyn Storage,
+    venue: &PoolVenue,
+    denom: &Denom,
+) -> StdResult<(Denom, Option<(Addr, Cw20Wrapper)>)> {
+    match (venue, denom) {
+        (PoolVenue::Osmosis, Denom::Cw20(cw20_addr)) => {
+            match CW20_WRAPPERS.may_load(storage, cw20_addr.clone())? {
+                Some(wrapper) => Ok((
+                    Denom::Native(wrapper.native_denom.clone()),
+                    Some((cw20_addr.clone(), wrapper)),
+                )),
+                None => Ok((denom.clone(), None)),
+            }
+        }
+        _ => Ok((denom.clone(), None)),
+    }
+}
+
+pub fn get_swap_msgs(
+    storage: &dyn Storage,
+    contract_addr: &Addr,
+    routes: &[OsmosisRoute],
+    offer: &AmountDenom,
     min_output: Uint128,
-    output_denom: Denom,
-) -> Result<CosmosMsg, ContractError> {
-    let input_denom = match input_denom {
-        Denom::Native(denom) => Ok(denom),
-        Denom::Cw20(_) => Err(ContractError::Std(StdError::GenericErr {
-            msg: "Not yet supported".into(),
-        })),
-    }?;
-    let output_denom = match output_denom {
+    output_denom: &Denom,
+) -> Result<PreparedSwap, ContractError> {
+    let venue = get_routes_venue(storage, routes)?;
+    let (offer_denom, offer_wrapper) = get_venue_denom(storage, &venue, &offer.denom)?;
+    let (swap_output_denom, output_wrapper) = get_venue_denom(storage, &venue, output_denom)?;
+    validate_routes(storage, routes, &swap_output_denom)?;
+
+    // Wrap the CW20 offer before the hops
+    let mut msgs = vec![];
+    if let Some((cw20_addr, wrapper)) = offer_wrapper {
+        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cw20_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: wrapper.converter.to_string(),
+                amount: offer.amount,
+                msg: to_binary(&Cw20ConverterHookMsg::Convert {})?,
+            })?,
+            funds: vec![],
+        }));
+    }
+    msgs.extend(venue.swap_venue().swap_msgs(
+        contract_addr,
+        routes,
+        &AmountDenom {
+            amount: offer.amount,
+            denom: offer_denom,
+        },
+        min_output,
+        &swap_output_denom,
+    )?);
+
+    // The output is unwrapped in the reply
+    Ok(PreparedSwap {
+        msgs,
+        output_denom: swap_output_denom,
+        unwrap: output_wrapper.map(|(cw20_addr, wrapper)| Cw20Unwrap {
+            cw20_addr,
+            converter: wrapper.converter,
+        }),
+    })
+}
+
+pub fn simulate_swap(
+    deps: Deps,
+    routes: &[OsmosisRoute],
+    offer: &AmountDenom,
+    output_denom: &Denom,
+) -> Result<SwapSimulation, ContractError> {
+    // Wrappers convert 1:1, so simulate the swap between the wrapped denoms
+    let venue = get_routes_venue(deps.storage, routes)?;
+    let (offer_denom, _) = get_venue_denom(deps.storage, &venue, &offer.denom)?;
+    let (swap_output_denom, _) = get_venue_denom(deps.storage, &venue, output_denom)?;
+    validate_routes(deps.storage, routes, &swap_output_denom)?;
+    Ok(venue.swap_venue().simulate(
+        &deps.querier,
+        routes,
+        &AmountDenom {
+            amount: offer.amount,
+            denom: offer_denom,
+        },
+    )?)
+}
+
+/// Minimum acceptable output for a swap along `routes`, derived from each hop's
+/// arithmetic TWAP over the last `TWAP_WINDOW_SECONDS` less `max_slippage_bps`.
+/// Fails if any pool on the route has no TWAP history for the window.
+pub fn get_twap_min_output(
+    deps: Deps,
+    env: &Env,
+    routes: &[OsmosisRoute],
+    input_amount: Uint128,
+    input_denom: &Denom,
+    max_slippage_bps: u64,
+) -> Result<Uint128, ContractError> {
+    if max_slippage_bps > MAX_BPS {
+        return Err(ContractError::InvalidSlippage {});
+    }
+    let mut denom_in = match get_venue_denom(deps.storage, &PoolVenue::Osmosis, input_denom)?.0 {
         Denom::Native(denom) => Ok(denom),
         Denom::Cw20(_) => Err(ContractError::Std(StdError::GenericErr {
             msg: "Not yet supported".into(),
         })),
     }?;
-    for (i, route) in routes.iter().enumerate() {
-        if !allowed_pool_ids.contains(&route.pool_id.clone()) {
-            return Err(ContractError::InvalidPoolId {
-                id: route.pool_id.clone(),
+
+    let start_time =
+        Timestamp::from_seconds(env.block.time.seconds().saturating_sub(TWAP_WINDOW_SECONDS));
+    let mut expected_output = Uint256::from(input_amount);
+    for route in routes.iter() {
+        // Only Osmosis pools keep a TWAP
+        if ALLOWED_POOLS.may_load(deps.storage, route.pool_id.clone())? != Some(PoolVenue::Osmosis)
+        {
+            return Err(ContractError::MissingTwap {
+                pool_id: route.pool_id.clone(),
             });
         }
-        // Quite important to check that the output denom is the last element
-        if (i == routes.len() - 1) && (route.token_out_denom != output_denom) {
-            return Err(ContractError::Std(StdError::GenericErr {
-                msg: "Output denom must be last element of routes".into(),
-            }));
-        }
+        let twap = query_arithmetic_twap_to_now(
+            &deps.querier,
+            parse_pool_id(&route.pool_id)?,
+            &denom_in,
+            &route.token_out_denom,
+            start_time,
+        )
+        .map_err(|_| ContractError::MissingTwap {
+            pool_id: route.pool_id.clone(),
+        })?;
+        expected_output = expected_output * twap;
+        denom_in = route.token_out_denom.clone();
     }
-    let msg = CosmosMsg::Custom(OsmosisSwap {
-        _type: "osmosis/gamm/swap-exact-amount-in".into(),
-        value: OsmosisSwapValue {
-            routes,
-            sender: contract_addr.into(),
-            token_in: Coin {
-                denom: input_denom,
-                amount: input_amount,
-            },
-            token_out_min_amount: min_output,
-        },
-    });
-    Ok(msg)
+
+    let slippage = Decimal256::from_ratio(MAX_BPS - max_slippage_bps, MAX_BPS);
+    let min_output = expected_output * slippage;
+    Uint128::try_from(min_output).map_err(|_| {
+        ContractError::Std(StdError::GenericErr {
+            msg: "Output amount overflow".to_string(),
+        })
+    })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg.id {
         SWAP_REPLY_ID => {
+            let mut queue = SWAP_QUEUE.may_load(deps.storage)?.unwrap_or_default();
+            if queue.is_empty() {
+                return Err(ContractError::Std(StdError::GenericErr {
+                    msg: "No pending swap".to_string(),
+                }));
+            }
             let SwapContext {
                 swapper_addr,
                 output_balance_before_swap,
                 output_denom,
-            } = SWAP_CTX.load(deps.storage)?;
+                min_output_amount,
+                keeper_tip,
+                unwrap,
+            } = queue.remove(0);
 
             // Output of Osmosis swap is the difference between
             // the output denom balance before and after swap
-            let output_balance_after_swap =
-                get_denom_balance(deps.as_ref(), output_denom.clone(), env.contract.address)?;
+            let output_balance_after_swap = get_denom_balance(
+                deps.as_ref(),
+                output_denom.clone(),
+                env.contract.address.clone(),
+            )?;
             let output_amount = output_balance_after_swap
                 .checked_sub(output_balance_before_swap)
                 .map_err(|_| {
@@ -541,18 +1767,107 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
                         msg: "Output amount overflow".to_string(),
                     })
                 })?;
+            // Fails the whole transaction, with every other swap of a keeper's batch
+            if output_amount < min_output_amount {
+                return Err(ContractError::InsufficientSwapOutput {});
+            }
+
+            // Balance changes from this reply's messages, which run before the next swap
+            let mut msgs: Vec<CosmosMsg> = vec![];
+            let mut inflows: Vec<AmountDenom> = vec![];
+            let mut outflows: Vec<AmountDenom> = vec![];
+
+            // Redeem a wrapped output back to its CW20, which converts 1:1
+            let credit_denom = match unwrap {
+                Some(Cw20Unwrap {
+                    cw20_addr,
+                    converter,
+                }) => {
+                    let native_denom = match &output_denom {
+                        Denom::Native(denom) => denom.clone(),
+                        Denom::Cw20(_) => {
+                            return Err(ContractError::Std(StdError::GenericErr {
+                                msg: "Wrapped output must be native".to_string(),
+                            }))
+                        }
+                    };
+                    if !output_amount.is_zero() {
+                        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                            contract_addr: converter.to_string(),
+                            msg: to_binary(&Cw20ConverterExecuteMsg::Convert {})?,
+                            funds: vec![Coin {
+                                amount: output_amount,
+                                denom: native_denom,
+                            }],
+                        }));
+                    }
+                    outflows.push(AmountDenom {
+                        amount: output_amount,
+                        denom: output_denom,
+                    });
+                    inflows.push(AmountDenom {
+                        amount: output_amount,
+                        denom: Denom::Cw20(cw20_addr.clone()),
+                    });
+                    Denom::Cw20(cw20_addr)
+                }
+                None => output_denom,
+            };
+
+            // Pay the keeper's tip out of the output
+            let tip_amount = match keeper_tip {
+                Some(KeeperTip { keeper, bps }) => {
+                    let tip_amount = output_amount.multiply_ratio(bps, MAX_BPS);
+                    if !tip_amount.is_zero() {
+                        msgs.push(get_send_msg(&credit_denom, &keeper, tip_amount)?);
+                    }
+                    tip_amount
+                }
+                None => Uint128::zero(),
+            };
+            outflows.push(AmountDenom {
+                amount: tip_amount,
+                denom: credit_denom.clone(),
+            });
 
             // Add output amount to locked balance
             add_amount_denom_to_locked_balance(
                 deps.storage,
                 swapper_addr.clone(),
                 AmountDenom {
-                    amount: output_amount,
-                    denom: output_denom.clone(),
+                    amount: output_amount - tip_amount,
+                    denom: credit_denom,
                 },
             )?;
 
-            Ok(Response::default())
+            // The next queued swap runs after this reply's messages
+            if let Some(next) = queue.first_mut() {
+                let mut balance = get_denom_balance(
+                    deps.as_ref(),
+                    next.output_denom.clone(),
+                    env.contract.address,
+                )?;
+                for inflow in inflows
+                    .iter()
+                    .filter(|flow| flow.denom == next.output_denom)
+                {
+                    balance += inflow.amount;
+                }
+                for outflow in outflows
+                    .iter()
+                    .filter(|flow| flow.denom == next.output_denom)
+                {
+                    balance -= outflow.amount;
+                }
+                next.output_balance_before_swap = balance;
+            }
+            if queue.is_empty() {
+                SWAP_QUEUE.remove(deps.storage);
+            } else {
+                SWAP_QUEUE.save(deps.storage, &queue)?;
+            }
+
+            Ok(Response::default().add_messages(msgs))
         }
         _ => Err(ContractError::Std(StdError::GenericErr {
             msg: "Unknown reply ID".to_string(),
@@ -560,6 +1875,26 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
     }
 }
 
+fn get_send_msg(denom: &Denom, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    match denom {
+        Denom::Native(denom) => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                amount,
+                denom: denom.to_string(),
+            }],
+        })),
+        Denom::Cw20(addr) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })),
+    }
+}
+
 fn get_denom_balance(deps: Deps, denom: Denom, target_addr: Addr) -> StdResult<Uint128> {
     match denom {
         Denom::Native(denom) => {
@@ -579,23 +1914,337 @@ fn get_denom_balance(deps: Deps, denom: Denom, target_addr: Addr) -> StdResult<U
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
-    unimplemented!()
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_schema::cw_serde;
-    use lib::msg::CircomProof;
-    use serde_json;
-
-    #[cw_serde]
-    pub struct ProofData {
-        pub proof: CircomProof,
-        pub public_signals: Vec<String>,
-    }
-
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Tiers {} => to_binary(&query_tiers(deps)?),
+        QueryMsg::Deposit {
+            tier_id,
+            credential,
+        } => to_binary(&query_deposit(deps, tier_id, credential)?),
+        QueryMsg::RootInfo { tier_id, root } => to_binary(&query_root_info(deps, tier_id, root)?),
+        QueryMsg::VerifyPath {
+            leaf,
+            root,
+            path_elements,
+            path_indices,
+        } => to_binary(&query_verify_path(leaf, root, path_elements, path_indices)?),
+        QueryMsg::SimulateSwap {
+            burner_addr,
+            routes,
+            input_amount,
+            input_denom,
+            output_denom,
+        } => to_binary(&query_simulate_swap(
+            deps,
+            burner_addr,
+            routes,
+            input_amount,
+            input_denom,
+            output_denom,
+        )?),
+        QueryMsg::LimitOrder { order_id } => to_binary(&query_limit_order(deps, order_id)?),
+        QueryMsg::LimitOrders { start_after, limit } => {
+            to_binary(&query_limit_orders(deps, start_after, limit)?)
+        }
+        QueryMsg::DcaSchedule { schedule_id } => to_binary(&query_dca_schedule(deps, schedule_id)?),
+        QueryMsg::DcaSchedules {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_dca_schedules(deps, owner, start_after, limit)?),
+    }
+}
+
+pub fn query_deposit(deps: Deps, tier_id: u32, credential: String) -> StdResult<DepositResponse> {
+    let deposit = CREDENTIALS.load(deps.storage, (tier_id, credential))?;
+    Ok(DepositResponse {
+        epoch: deposit.epoch,
+        leaf_index: deposit.leaf_index,
+        height: deposit.height,
+        withdraw_addr: deposit.withdraw_addr.to_string(),
+    })
+}
+
+pub fn query_root_info(deps: Deps, tier_id: u32, root: String) -> StdResult<RootInfoResponse> {
+    let root = Uint256::from_str(&root)?;
+    let root_info = ROOT_INFO.load(deps.storage, (tier_id, root.to_string()))?;
+    Ok(RootInfoResponse {
+        height: root_info.height,
+        anonymity_set: root_info.leaves,
+        epoch: root_info.epoch,
+    })
+}
+
+pub fn query_verify_path(
+    leaf: String,
+    root: String,
+    path_elements: Vec<String>,
+    path_indices: Vec<String>,
+) -> StdResult<VerifyPathResponse> {
+    let parse = |values: Vec<String>| -> StdResult<Vec<Uint256>> {
+        values
+            .iter()
+            .map(|value| Uint256::from_str(value))
+            .collect()
+    };
+    let path_elements = parse(path_elements)?;
+    let path_indices = parse(path_indices)?;
+
+    // The circuits only accept paths through the whole tree
+    let valid = path_elements.len() == TREE_LEVELS as usize
+        && verify_path(
+            &Uint256::from_str(&leaf)?,
+            &path_elements,
+            &path_indices,
+            &Uint256::from_str(&root)?,
+        );
+    Ok(VerifyPathResponse { valid })
+}
+
+pub fn query_tiers(deps: Deps) -> StdResult<TiersResponse> {
+    let tiers = TIERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (tier_id, tier) = item?;
+            // Deposits of every epoch
+            let deposit_count = EPOCH_TREES
+                .prefix(tier_id)
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| Ok(COMMITMENTS.meta(deps.storage, item?.1)?.next_index))
+                .sum::<StdResult<u32>>()?;
+            Ok(TierResponse {
+                tier_id,
+                tier,
+                epoch: CURRENT_EPOCHS.load(deps.storage, tier_id)?,
+                deposit_count,
+                withdrawal_count: WITHDRAWAL_COUNTS.load(deps.storage, tier_id)?,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TiersResponse { tiers })
+}
+
+pub fn query_dca_schedule(deps: Deps, schedule_id: u64) -> StdResult<DcaScheduleResponse> {
+    let schedule = DCA_SCHEDULES.load(deps.storage, schedule_id)?;
+    Ok(DcaScheduleResponse {
+        schedule_id,
+        schedule,
+    })
+}
+
+pub fn query_dca_schedules(
+    deps: Deps,
+    owner: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DcaSchedulesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let schedule_ids = match owner {
+        Some(owner) => {
+            let owner = deps.api.addr_validate(&owner)?;
+            DCA_SCHEDULES_BY_OWNER
+                .prefix(owner)
+                .keys(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?
+        }
+        None => DCA_SCHEDULES
+            .keys(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+    let schedules = schedule_ids
+        .into_iter()
+        .map(|schedule_id| query_dca_schedule(deps, schedule_id))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(DcaSchedulesResponse { schedules })
+}
+
+pub fn query_limit_order(deps: Deps, order_id: u64) -> StdResult<LimitOrderResponse> {
+    let order = LIMIT_ORDERS.load(deps.storage, order_id)?;
+    Ok(LimitOrderResponse { order_id, order })
+}
+
+pub fn query_limit_orders(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<LimitOrdersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let orders = LIMIT_ORDERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(order_id, order)| LimitOrderResponse { order_id, order }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(LimitOrdersResponse { orders })
+}
+
+pub fn query_simulate_swap(
+    deps: Deps,
+    burner_addr: String,
+    routes: Vec<OsmosisRoute>,
+    input_amount: Uint128,
+    input_denom: DenomUnvalidated,
+    output_denom: DenomUnvalidated,
+) -> StdResult<SimulateSwapResponse> {
+    let burner_addr = deps.api.addr_validate(&burner_addr)?;
+    let input_denom = input_denom.validate(deps.api)?;
+    let output_denom = output_denom.validate(deps.api)?;
+
+    let locked_balance = get_locked_balance(deps.storage, burner_addr, &input_denom)?;
+    let simulation = simulate_swap(
+        deps,
+        &routes,
+        &AmountDenom {
+            amount: input_amount,
+            denom: input_denom,
+        },
+        &output_denom,
+    )
+    .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    Ok(SimulateSwapResponse {
+        output_amount: simulation.output_amount,
+        price_impact: simulation.price_impact,
+        locked_balance_sufficient: locked_balance >= input_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::OsmosisSwapValue;
+    use crate::msg::PoolVenueUnvalidated;
+    use crate::msg::TierMsg;
+    use crate::testing::{mock_dependencies_with_twap, setup_mock_pair, MockCircuit};
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coins, from_slice, Attribute, Empty};
+    use cw_multi_test::{BasicApp, BasicAppBuilder, ContractWrapper, Executor};
+    use lib::msg::CircomProof;
+    use serde_json;
+
+    #[cw_serde]
+    pub struct ProofData {
+        pub proof: CircomProof,
+        pub public_signals: Vec<String>,
+    }
+
+    struct Circuits {
+        deposit: MockCircuit,
+        migrate: MockCircuit,
+        withdraw: MockCircuit,
+        reclaim: MockCircuit,
+    }
+
+    fn mock_circuits() -> Circuits {
+        Circuits {
+            deposit: MockCircuit::new(3),
+            migrate: MockCircuit::new(4),
+            withdraw: MockCircuit::new(2),
+            reclaim: MockCircuit::new(4),
+        }
+    }
+
+    // A single 100 uusdc tier, verified by `circuits`
+    fn mock_instantiate_msg(circuits: &Circuits) -> InstantiateMsg {
+        InstantiateMsg {
+            tiers: vec![TierMsg {
+                denom: DenomUnvalidated::Native("uusdc".into()),
+                amount: Uint128::from(100u128),
+            }],
+            pool_admin: Some("admin".into()),
+            allowed_pools: vec![],
+            cw20_wrappers: vec![],
+            vk_deposit: circuits.deposit.vk_json(),
+            vk_swap_deposit: circuits.migrate.vk_json(),
+            vk_withdraw: circuits.withdraw.vk_json(),
+            vk_reclaim: circuits.reclaim.vk_json(),
+            reclaim_delay_blocks: 100,
+            migration_policy: None,
+            root_expiry: None,
+        }
+    }
+
+    // Withdraw addresses are numeric, as `poseidon_hash` passes them through as signals
+    fn mock_deposit(
+        deps: DepsMut,
+        circuits: &Circuits,
+        env: Env,
+        tier_id: u32,
+        credential: &str,
+        withdraw_addr: &str,
+    ) -> Result<Response, ContractError> {
+        let proof =
+            circuits
+                .deposit
+                .prove(&[withdraw_addr.into(), credential.into(), tier_id.to_string()]);
+        let amount = TIERS.load(deps.storage, tier_id)?.amount;
+        execute_deposit(
+            deps,
+            mock_info("depositor", &coins(amount.u128(), "uusdc")),
+            env,
+            tier_id,
+            proof.to_proof(),
+            credential.into(),
+            Addr::unchecked(withdraw_addr),
+        )
+    }
+
+    #[test]
+    fn test_execute_deposit() {
+        let circuits = mock_circuits();
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            mock_instantiate_msg(&circuits),
+        )
+        .unwrap();
+
+        let res = mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "42", "1234").unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("commitment")
+                .add_attribute("tier_id", "0")
+                .add_attribute("epoch", "0")
+                .add_attribute("leaf_index", "0")
+                .add_attribute("commitment", "42")]
+        );
+        let deposit = query_deposit(deps.as_ref(), 0, "42".into()).unwrap();
+        assert_eq!(deposit.leaf_index, 0);
+        assert_eq!(deposit.height, mock_env().block.height);
+        assert_eq!(deposit.withdraw_addr, "1234");
+        let root = COMMITMENTS.get_last_root(deps.as_ref().storage, 0).unwrap();
+        let root_info = query_root_info(deps.as_ref(), 0, root.to_string()).unwrap();
+        assert_eq!(root_info.anonymity_set, 1);
+
+        // The tier id is a public signal, so a proof for another tier is rejected
+        let proof = circuits
+            .deposit
+            .prove(&["1234".into(), "43".into(), "1".into()]);
+        let res = execute_deposit(
+            deps.as_mut(),
+            mock_info("depositor", &coins(100, "uusdc")),
+            mock_env(),
+            0,
+            proof.to_proof(),
+            "43".into(),
+            Addr::unchecked("1234"),
+        );
+        assert!(matches!(res, Err(ContractError::InvalidProof {})));
+
+        // Each credential can only be deposited once
+        let res = mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "42", "1234");
+        assert!(matches!(
+            res,
+            Err(ContractError::InvalidDepositCredential {})
+        ));
+    }
+
     #[test]
     fn test_deposit() {
         let deposit_vk: &str = include_str!("../../../circuits/verification_keys/deposit.vk.json");
@@ -612,7 +2261,7 @@ mod tests {
         let res = v
             .clone()
             .verify_proof(proof.clone().to_proof(), &public_signals.clone().get());
-        assert_eq!(res, true);
+        assert!(res);
 
         // Bad public signal address
         let bad_signals = PublicSignals(vec![
@@ -621,6 +2270,1187 @@ mod tests {
             public_signals.0[1].clone(),
         ]);
         let res = v.verify_proof(proof.to_proof(), &bad_signals.get());
-        assert_eq!(res, false);
+        assert!(!res);
+    }
+
+    fn mock_migrate_deposit(
+        deps: DepsMut,
+        circuits: &Circuits,
+        env: Env,
+        tier_id: u32,
+        root: &str,
+        nullifier: &str,
+    ) -> Result<Response, ContractError> {
+        let proof = circuits.migrate.prove(&[
+            root.into(),
+            nullifier.into(),
+            "0".into(),
+            tier_id.to_string(),
+        ]);
+        execute_migrate_deposit(
+            deps,
+            mock_info("burner", &[]),
+            env,
+            tier_id,
+            proof.to_proof(),
+            root.into(),
+            nullifier.into(),
+        )
+    }
+
+    fn mock_reclaim(
+        deps: DepsMut,
+        circuits: &Circuits,
+        env: Env,
+        credential: &str,
+        nullifier: &str,
+    ) -> Result<Response, ContractError> {
+        let withdraw_addr = CREDENTIALS
+            .load(deps.storage, (0, credential.into()))?
+            .withdraw_addr;
+        let proof = circuits.reclaim.prove(&[
+            withdraw_addr.to_string(),
+            credential.into(),
+            nullifier.into(),
+            "0".into(),
+        ]);
+        execute_reclaim(
+            deps,
+            mock_info("anyone", &[]),
+            env,
+            0,
+            proof.to_proof(),
+            credential.into(),
+            nullifier.into(),
+        )
+    }
+
+    #[test]
+    fn test_receive_deposit() {
+        let circuits = mock_circuits();
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            InstantiateMsg {
+                tiers: vec![TierMsg {
+                    denom: DenomUnvalidated::Cw20("token".into()),
+                    amount: Uint128::from(100u128),
+                }],
+                ..mock_instantiate_msg(&circuits)
+            },
+        )
+        .unwrap();
+        let receive = |deps: DepsMut, cw20_addr: &str, amount: u128, credential: &str| {
+            let proof = circuits
+                .deposit
+                .prove(&["1234".into(), credential.into(), "0".into()]);
+            execute(
+                deps,
+                mock_env(),
+                mock_info(cw20_addr, &[]),
+                ExecuteMsg::Receive(Cw20ReceiveMsg {
+                    sender: "depositor".into(),
+                    amount: Uint128::from(amount),
+                    msg: to_binary(&ReceiveMsg::Deposit(DepositMsg {
+                        tier_id: 0,
+                        proof,
+                        credential: credential.into(),
+                        withdraw_addr: "1234".into(),
+                    }))
+                    .unwrap(),
+                }),
+            )
+        };
+
+        // Tokens must come from the tier's CW20 contract
+        let res = receive(deps.as_mut(), "other", 100, "42");
+        assert!(matches!(res, Err(ContractError::InvalidDeposit {})));
+        // In the tier's amount
+        let res = receive(deps.as_mut(), "token", 99, "42");
+        assert!(matches!(res, Err(ContractError::InvalidDeposit {})));
+
+        let res = receive(deps.as_mut(), "token", 100, "42").unwrap();
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("from", "depositor")));
+        let deposit = query_deposit(deps.as_ref(), 0, "42".into()).unwrap();
+        assert_eq!(deposit.leaf_index, 0);
+
+        // CW20 tiers only take deposits through `Receive`
+        let res = mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "43", "1234");
+        assert!(matches!(res, Err(ContractError::InvalidDeposit {})));
+    }
+
+    #[test]
+    fn test_reclaim() {
+        let circuits = mock_circuits();
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            mock_instantiate_msg(&circuits),
+        )
+        .unwrap();
+        mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "42", "1234").unwrap();
+        let mut env = mock_env();
+
+        // Deposits can only be reclaimed once they are `reclaim_delay_blocks` old
+        env.block.height += 99;
+        let res = mock_reclaim(deps.as_mut(), &circuits, env.clone(), "42", "9");
+        assert!(matches!(res, Err(ContractError::ReclaimTooEarly {})));
+
+        // The deposit goes back to its withdraw address, whoever sends the reclaim
+        env.block.height += 1;
+        let res = mock_reclaim(deps.as_mut(), &circuits, env.clone(), "42", "9").unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "1234".into(),
+                amount: coins(100, "uusdc"),
+            })]
+        );
+        let res = mock_reclaim(deps.as_mut(), &circuits, env.clone(), "42", "9");
+        assert!(matches!(res, Err(ContractError::DuplicateCommitment {})));
+
+        // Reclaims spend the nullifier of a migration, so a reclaimed deposit can't be migrated
+        let root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 0)
+            .unwrap()
+            .to_string();
+        let res = mock_migrate_deposit(deps.as_mut(), &circuits, env.clone(), 0, &root, "9");
+        assert!(matches!(res, Err(ContractError::DuplicateCommitment {})));
+
+        // Nor can a migrated deposit be reclaimed
+        mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "43", "1234").unwrap();
+        let root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 0)
+            .unwrap()
+            .to_string();
+        mock_migrate_deposit(deps.as_mut(), &circuits, env.clone(), 0, &root, "10").unwrap();
+        let res = mock_reclaim(deps.as_mut(), &circuits, env, "43", "10");
+        assert!(matches!(res, Err(ContractError::DuplicateCommitment {})));
+
+        // Both deposits left the tier, one reclaimed and one migrated
+        let res = query_tiers(deps.as_ref()).unwrap();
+        assert_eq!(res.tiers[0].deposit_count, 2);
+        assert_eq!(res.tiers[0].withdrawal_count, 2);
+    }
+
+    #[test]
+    fn test_tiers() {
+        let vk: &str = include_str!("../../../circuits/verification_keys/deposit.vk.json");
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            InstantiateMsg {
+                tiers: vec![
+                    TierMsg {
+                        denom: DenomUnvalidated::Native("uusdc".into()),
+                        amount: Uint128::from(100u128),
+                    },
+                    TierMsg {
+                        denom: DenomUnvalidated::Native("uusdc".into()),
+                        amount: Uint128::from(1_000u128),
+                    },
+                ],
+                pool_admin: None,
+                allowed_pools: vec![],
+                cw20_wrappers: vec![],
+                vk_deposit: vk.to_string(),
+                vk_swap_deposit: vk.to_string(),
+                vk_withdraw: vk.to_string(),
+                vk_reclaim: vk.to_string(),
+                reclaim_delay_blocks: 100_000,
+                migration_policy: None,
+                root_expiry: None,
+            },
+        )
+        .unwrap();
+
+        let res = query_tiers(deps.as_ref()).unwrap();
+        assert_eq!(res.tiers.len(), 2);
+        assert_eq!(res.tiers[1].tier_id, 1);
+        assert_eq!(res.tiers[1].tier.amount, Uint128::from(1_000u128));
+        assert_eq!(res.tiers[1].deposit_count, 0);
+
+        // Funds must match the selected tier
+        let proof_data_json: ProofData = serde_json::from_str(include_str!(
+            "../../../generate-proofs/outputs/deposit1.json"
+        ))
+        .unwrap();
+        let res = execute_deposit(
+            deps.as_mut(),
+            mock_info("depositor", &coins(100, "uusdc")),
+            mock_env(),
+            1,
+            proof_data_json.proof.clone().to_proof(),
+            proof_data_json.public_signals[1].clone(),
+            Addr::unchecked("withdraw"),
+        );
+        assert!(matches!(res, Err(ContractError::InvalidDeposit {})));
+
+        let res = execute_deposit(
+            deps.as_mut(),
+            mock_info("depositor", &coins(100, "uusdc")),
+            mock_env(),
+            2,
+            proof_data_json.proof.to_proof(),
+            proof_data_json.public_signals[1].clone(),
+            Addr::unchecked("withdraw"),
+        );
+        assert!(matches!(res, Err(ContractError::UnknownTier { id: 2 })));
+    }
+
+    // Storage of the contract from before deposit tiers, with a 100 uusdc pool
+    fn save_single_pool(
+        storage: &mut dyn Storage,
+        tree: &lib::merkle_tree::MerkleTreeWithHistory,
+        vk_migrate: String,
+    ) {
+        LEGACY_DEPOSIT_AMOUNT
+            .save(storage, &Uint128::from(100u128))
+            .unwrap();
+        LEGACY_DEPOSIT_DENOM
+            .save(storage, &Denom::Native("uusdc".into()))
+            .unwrap();
+        LEGACY_COMMITMENT_TREE.save(storage, tree).unwrap();
+        MIGRATE_VERIFIER
+            .save(storage, &Verifier::from_vk(vk_migrate))
+            .unwrap();
+    }
+
+    // Adds tier ids to the verifying keys and configures reclaims
+    fn mock_tier_migrate_msg(circuits: &Circuits) -> MigrateMsg {
+        MigrateMsg {
+            vk_deposit: Some(circuits.deposit.vk_json()),
+            vk_swap_deposit: Some(circuits.migrate.vk_json()),
+            vk_reclaim: Some(circuits.reclaim.vk_json()),
+            reclaim_delay_blocks: Some(100),
+            ..MigrateMsg::default()
+        }
+    }
+
+    #[test]
+    fn test_migrate_single_pool() {
+        let circuits = mock_circuits();
+        // Proves [root, nullifier, previous nullifier], without a tier id
+        let legacy_migrate = MockCircuit::new(3);
+        let mut deps = mock_dependencies();
+        let mut tree = lib::merkle_tree::MerkleTreeWithHistory::new(20);
+        tree.insert(&Uint256::from(42u128)).unwrap();
+        let old_root = tree.get_last_root();
+        tree.insert(&Uint256::from(43u128)).unwrap();
+        let storage = deps.as_mut().storage;
+        save_single_pool(storage, &tree, legacy_migrate.vk_json());
+        LEGACY_NULLIFIERS.save(storage, "7".into(), &true).unwrap();
+        LEGACY_ALLOWED_POOLS
+            .save(storage, &vec!["1".into(), "7".into()])
+            .unwrap();
+
+        // The old keys can't prove tier ids, so new ones must be given
+        let res = migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                vk_reclaim: Some(circuits.reclaim.vk_json()),
+                reclaim_delay_blocks: Some(100),
+                ..MigrateMsg::default()
+            },
+        );
+        assert!(res.is_err());
+        migrate(deps.as_mut(), mock_env(), mock_tier_migrate_msg(&circuits)).unwrap();
+
+        assert!(LEGACY_DEPOSIT_AMOUNT
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+        assert!(LEGACY_COMMITMENT_TREE
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+        let res = query_tiers(deps.as_ref()).unwrap();
+        assert_eq!(res.tiers.len(), 1);
+        assert_eq!(res.tiers[0].tier.amount, Uint128::from(100u128));
+        assert_eq!(res.tiers[0].deposit_count, 2);
+        assert_eq!(res.tiers[0].withdrawal_count, 1);
+        let root_info = query_root_info(deps.as_ref(), 0, old_root.to_string()).unwrap();
+        assert_eq!(root_info.anonymity_set, 1);
+        assert_eq!(root_info.height, mock_env().block.height);
+        assert_eq!(root_info.epoch, 0);
+        // Allowed pools from before venues are Osmosis pools
+        assert!(LEGACY_ALLOWED_POOLS
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            ALLOWED_POOLS
+                .range(deps.as_ref().storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap(),
+            vec![
+                ("1".to_string(), PoolVenue::Osmosis),
+                ("7".to_string(), PoolVenue::Osmosis)
+            ]
+        );
+
+        // New deposits go to a fresh epoch and are proven with their tier id
+        mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "44", "1234").unwrap();
+        let new_root = COMMITMENTS.get_last_root(deps.as_ref().storage, 1).unwrap();
+        let root_info = query_root_info(deps.as_ref(), 0, new_root.to_string()).unwrap();
+        assert_eq!(root_info.epoch, 1);
+        mock_migrate_deposit(
+            deps.as_mut(),
+            &circuits,
+            mock_env(),
+            0,
+            &new_root.to_string(),
+            "9",
+        )
+        .unwrap();
+
+        // Deposits made before the migration are proven with the old key, against
+        // their old roots
+        let res = mock_migrate_deposit(
+            deps.as_mut(),
+            &circuits,
+            mock_env(),
+            0,
+            &old_root.to_string(),
+            "8",
+        );
+        assert!(matches!(res, Err(ContractError::InvalidProof {})));
+        let migrate_deposit = |deps: DepsMut, nullifier: &str| {
+            let proof = legacy_migrate.prove(&[old_root.to_string(), nullifier.into(), "0".into()]);
+            execute_migrate_deposit(
+                deps,
+                mock_info("holder", &[]),
+                mock_env(),
+                0,
+                proof.to_proof(),
+                old_root.to_string(),
+                nullifier.into(),
+            )
+        };
+        migrate_deposit(deps.as_mut(), "8").unwrap();
+        assert_eq!(
+            get_locked_balance(
+                deps.as_ref().storage,
+                Addr::unchecked("holder"),
+                &Denom::Native("uusdc".into())
+            )
+            .unwrap(),
+            Uint128::from(100u128)
+        );
+
+        // Nullifiers spent before the migration stay spent
+        let res = migrate_deposit(deps.as_mut(), "7");
+        assert!(matches!(res, Err(ContractError::DuplicateCommitment {})));
+    }
+
+    #[test]
+    fn test_migrate_single_pool_note() {
+        // The committed proof of the circuit from before deposit tiers, for the note
+        // Poseidon(1337, 8000)
+        let proof_data_json: ProofData = serde_json::from_str(include_str!(
+            "../../../generate-proofs/outputs/legacy/migrate1.json"
+        ))
+        .unwrap();
+        let vk = include_str!("../../../circuits/verification_keys/legacy/migrate.vk.json");
+        let [root, nullifier, previous_nullifier]: [String; 3] =
+            proof_data_json.public_signals.clone().try_into().unwrap();
+
+        let mut deps = mock_dependencies();
+        let mut tree = lib::merkle_tree::MerkleTreeWithHistory::new(20);
+        let leaf = lib::poseidon::Poseidon::new()
+            .hash_u256(&[Uint256::from(1337u128), Uint256::from(8000u128)])
+            .unwrap();
+        tree.insert(&leaf).unwrap();
+        assert_eq!(tree.get_last_root().to_string(), root);
+        save_single_pool(deps.as_mut().storage, &tree, vk.to_string());
+        MAP_ADDR_TO_PREVIOUS_NULLIFIER
+            .save(
+                deps.as_mut().storage,
+                Addr::unchecked("burner"),
+                &Uint256::from_str(&previous_nullifier).unwrap(),
+            )
+            .unwrap();
+        migrate(
+            deps.as_mut(),
+            mock_env(),
+            mock_tier_migrate_msg(&mock_circuits()),
+        )
+        .unwrap();
+
+        let migrate_deposit = |deps: DepsMut| {
+            execute_migrate_deposit(
+                deps,
+                mock_info("burner", &[]),
+                mock_env(),
+                0,
+                proof_data_json.proof.clone().to_proof(),
+                root.clone(),
+                nullifier.clone(),
+            )
+        };
+        migrate_deposit(deps.as_mut()).unwrap();
+        assert_eq!(
+            get_locked_balance(
+                deps.as_ref().storage,
+                Addr::unchecked("burner"),
+                &Denom::Native("uusdc".into())
+            )
+            .unwrap(),
+            Uint128::from(100u128)
+        );
+        let res = migrate_deposit(deps.as_mut());
+        assert!(matches!(res, Err(ContractError::DuplicateCommitment {})));
+    }
+
+    #[test]
+    fn test_query_verify_path() {
+        let mut mt = lib::merkle_tree::MerkleTreeWithHistory::new(20);
+        let (_, path_indices, path_elements) =
+            mt.insert_and_return_path(&Uint256::from(42u128)).unwrap();
+        let to_strings = |values: &Vec<Uint256>| -> Vec<String> {
+            values.iter().map(|v| v.to_string()).collect()
+        };
+
+        let res = query_verify_path(
+            "42".to_string(),
+            mt.get_last_root().to_string(),
+            to_strings(&path_elements),
+            to_strings(&path_indices),
+        )
+        .unwrap();
+        assert!(res.valid);
+
+        let res = query_verify_path(
+            "43".to_string(),
+            mt.get_last_root().to_string(),
+            to_strings(&path_elements),
+            to_strings(&path_indices),
+        )
+        .unwrap();
+        assert!(!res.valid);
+    }
+
+    #[test]
+    fn test_tree_epochs() {
+        let mut deps = mock_dependencies();
+        let storage = deps.as_mut().storage;
+        TIERS
+            .save(
+                storage,
+                0,
+                &Tier {
+                    denom: Denom::Native("uusdc".into()),
+                    amount: Uint128::from(100u128),
+                },
+            )
+            .unwrap();
+        // A tree of two leaves, so it fills quickly
+        COMMITMENTS.init(storage, 0, 1, 10).unwrap();
+        EPOCH_TREES.save(storage, (0, 0), &0).unwrap();
+        CURRENT_EPOCHS.save(storage, 0, &0).unwrap();
+        NEXT_TREE_ID.save(storage, &1).unwrap();
+        WITHDRAWAL_COUNTS.save(storage, 0, &0).unwrap();
+
+        assert_eq!(get_deposit_tree(storage, 0, 2).unwrap(), (0, 0));
+        COMMITMENTS
+            .insert_batch(storage, 0, &[Uint256::one()])
+            .unwrap();
+        let first_root = COMMITMENTS.get_last_root(storage, 0).unwrap();
+
+        // A batch that doesn't fit starts the next epoch, leaving the old tree as is
+        assert_eq!(get_deposit_tree(storage, 0, 2).unwrap(), (1, 1));
+        assert_eq!(CURRENT_EPOCHS.load(storage, 0).unwrap(), 1);
+        assert_eq!(COMMITMENTS.meta(storage, 1).unwrap().levels, 1);
+        assert!(COMMITMENTS.is_known_root(storage, 0, &first_root).unwrap());
+        assert_eq!(get_deposit_tree(storage, 0, 2).unwrap(), (1, 1));
+
+        COMMITMENTS
+            .insert_batch(storage, 1, &[Uint256::one(), Uint256::one()])
+            .unwrap();
+        let res = query_tiers(deps.as_ref()).unwrap();
+        assert_eq!(res.tiers[0].epoch, 1);
+        assert_eq!(res.tiers[0].deposit_count, 3);
+    }
+
+    #[test]
+    fn test_migrate_from_filled_epoch() {
+        let circuits = mock_circuits();
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            InstantiateMsg {
+                root_expiry: Some(RootExpiry::Blocks { blocks: 10 }),
+                ..mock_instantiate_msg(&circuits)
+            },
+        )
+        .unwrap();
+        // Swap the tier's tree for one of two leaves, so it fills quickly
+        let storage = deps.as_mut().storage;
+        COMMITMENTS.init(storage, 1, 1, 1).unwrap();
+        EPOCH_TREES.save(storage, (0, 0), &1).unwrap();
+        NEXT_TREE_ID.save(storage, &2).unwrap();
+
+        mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "1", "1234").unwrap();
+        let first_root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 1)
+            .unwrap()
+            .to_string();
+        mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "2", "1234").unwrap();
+        let final_root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 1)
+            .unwrap()
+            .to_string();
+
+        // The next deposit starts epoch 1, which its event and queries report
+        let res = mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "3", "1234").unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("commitment")
+                .add_attribute("tier_id", "0")
+                .add_attribute("epoch", "1")
+                .add_attribute("leaf_index", "0")
+                .add_attribute("commitment", "3")]
+        );
+        let deposit = query_deposit(deps.as_ref(), 0, "3".into()).unwrap();
+        assert_eq!((deposit.epoch, deposit.leaf_index), (1, 0));
+        let epoch_root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 2)
+            .unwrap()
+            .to_string();
+        let root_info = query_root_info(deps.as_ref(), 0, epoch_root.clone()).unwrap();
+        assert_eq!((root_info.epoch, root_info.anonymity_set), (1, 1));
+        let root_info = query_root_info(deps.as_ref(), 0, final_root.clone()).unwrap();
+        assert_eq!((root_info.epoch, root_info.anonymity_set), (0, 2));
+        assert_eq!(query_tiers(deps.as_ref()).unwrap().tiers[0].epoch, 1);
+
+        // Once roots expire, the final root of the filled epoch is still accepted
+        let mut env = mock_env();
+        env.block.height += 11;
+        mock_migrate_deposit(deps.as_mut(), &circuits, env.clone(), 0, &final_root, "8").unwrap();
+        let res = mock_migrate_deposit(deps.as_mut(), &circuits, env.clone(), 0, &first_root, "9");
+        assert!(matches!(res, Err(ContractError::UnknownRoot {})));
+        let res = mock_migrate_deposit(deps.as_mut(), &circuits, env, 0, &epoch_root, "9");
+        assert!(matches!(res, Err(ContractError::UnknownRoot {})));
+    }
+
+    #[test]
+    fn test_migration_policy() {
+        let circuits = mock_circuits();
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            InstantiateMsg {
+                migration_policy: Some(MigrationPolicy {
+                    min_root_age_blocks: Some(10),
+                    min_anonymity_set: Some(2),
+                }),
+                ..mock_instantiate_msg(&circuits)
+            },
+        )
+        .unwrap();
+        let mut env = mock_env();
+
+        // Roots made with fewer leaves than the anonymity set are rejected at any age
+        mock_deposit(deps.as_mut(), &circuits, mock_env(), 0, "1", "1234").unwrap();
+        let root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 0)
+            .unwrap()
+            .to_string();
+        env.block.height += 10;
+        let res = mock_migrate_deposit(deps.as_mut(), &circuits, env.clone(), 0, &root, "8");
+        assert!(matches!(res, Err(ContractError::AnonymitySetTooSmall {})));
+
+        // Roots must also be old enough
+        mock_deposit(deps.as_mut(), &circuits, env.clone(), 0, "2", "1234").unwrap();
+        let root = COMMITMENTS
+            .get_last_root(deps.as_ref().storage, 0)
+            .unwrap()
+            .to_string();
+        env.block.height += 9;
+        let res = mock_migrate_deposit(deps.as_mut(), &circuits, env.clone(), 0, &root, "8");
+        assert!(matches!(res, Err(ContractError::RootTooRecent {})));
+        env.block.height += 1;
+        mock_migrate_deposit(deps.as_mut(), &circuits, env, 0, &root, "8").unwrap();
+    }
+
+    #[test]
+    fn test_add_keeper_tip() {
+        // 1% of 1_000 leaves 990
+        assert_eq!(
+            add_keeper_tip(Uint128::from(990u128), 100),
+            Some(Uint128::from(1_000u128))
+        );
+        // Rounded up so the net output still meets the limit
+        let gross = add_keeper_tip(Uint128::from(1_000u128), 300).unwrap();
+        assert_eq!(gross, Uint128::from(1_031u128));
+        assert!(gross - gross.multiply_ratio(300u64, MAX_BPS) >= Uint128::from(1_000u128));
+        assert_eq!(
+            add_keeper_tip(Uint128::from(1_000u128), 0),
+            Some(Uint128::from(1_000u128))
+        );
+        assert_eq!(add_keeper_tip(Uint128::MAX, 100), None);
+    }
+
+    #[test]
+    fn test_get_batch_amount() {
+        let tier = Tier {
+            denom: Denom::Native("uusdc".into()),
+            amount: Uint128::from(1_000u128),
+        };
+        assert_eq!(
+            get_batch_amount(&tier, 100).unwrap(),
+            Uint128::from(100_000u128)
+        );
+
+        // Batches can't be empty
+        let res = get_batch_amount(&tier, 0);
+        assert!(matches!(res, Err(ContractError::InvalidDeposit {})));
+    }
+
+    #[test]
+    fn test_validate_routes() {
+        let mut deps = mock_dependencies();
+        ALLOWED_POOLS
+            .save(deps.as_mut().storage, "1".into(), &PoolVenue::Osmosis)
+            .unwrap();
+        ALLOWED_POOLS
+            .save(deps.as_mut().storage, "7".into(), &PoolVenue::Osmosis)
+            .unwrap();
+        ALLOWED_POOLS
+            .save(
+                deps.as_mut().storage,
+                "astro".into(),
+                &PoolVenue::Astroport {
+                    pair_addr: Addr::unchecked("pair"),
+                },
+            )
+            .unwrap();
+
+        let routes = vec![
+            OsmosisRoute {
+                pool_id: "1".into(),
+                token_out_denom: "uatom".into(),
+            },
+            OsmosisRoute {
+                pool_id: "7".into(),
+                token_out_denom: "uusdc".into(),
+            },
+        ];
+        let uusdc = Denom::Native("uusdc".into());
+        let venue = validate_routes(&deps.storage, &routes, &uusdc).unwrap();
+        assert_eq!(venue, PoolVenue::Osmosis);
+
+        // Last hop must end in the output denom
+        let res = validate_routes(&deps.storage, &routes, &Denom::Native("uatom".into()));
+        assert!(res.is_err());
+
+        // Every hop must go through an allowed pool
+        let mut bad_routes = routes.clone();
+        bad_routes[1].pool_id = "8".into();
+        let res = validate_routes(&deps.storage, &bad_routes, &uusdc);
+        assert!(matches!(res, Err(ContractError::InvalidPoolId { id }) if id == "8"));
+
+        // Hops can't mix venues
+        let mut mixed_routes = routes;
+        mixed_routes[1].pool_id = "astro".into();
+        let res = validate_routes(&deps.storage, &mixed_routes, &uusdc);
+        assert!(matches!(res, Err(ContractError::MixedVenues {})));
+    }
+
+    // A contract with a 100 uusdc tier, and a mock Astroport pair "astro" paying 2 uosmo
+    // per uusdc. "burner" has migrated a deposit, so it has 100 uusdc locked
+    fn setup_swap_app(circuits: &Circuits) -> (BasicApp<OsmosisSwap, Empty>, Addr) {
+        let mut app =
+            BasicAppBuilder::<OsmosisSwap, Empty>::new_custom().build(|router, _, storage| {
+                router
+                    .bank
+                    .init_balance(storage, &Addr::unchecked("depositor"), coins(100, "uusdc"))
+                    .unwrap();
+            });
+        let pair_addr = setup_mock_pair(&mut app, "uosmo");
+        let code_id = app.store_code(Box::new(
+            ContractWrapper::new(execute, instantiate, query).with_reply(reply),
+        ));
+        let contract_addr = app
+            .instantiate_contract(
+                code_id,
+                Addr::unchecked("creator"),
+                &InstantiateMsg {
+                    allowed_pools: vec![AllowedPool {
+                        pool_id: "astro".into(),
+                        venue: PoolVenueUnvalidated::Astroport {
+                            pair_addr: pair_addr.to_string(),
+                        },
+                    }],
+                    ..mock_instantiate_msg(circuits)
+                },
+                &[],
+                "whirlwind",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked("depositor"),
+            contract_addr.clone(),
+            &ExecuteMsg::Deposit {
+                tier_id: 0,
+                proof: circuits
+                    .deposit
+                    .prove(&["1234".into(), "42".into(), "0".into()]),
+                credential: "42".into(),
+                withdraw_addr: "1234".into(),
+            },
+            &coins(100, "uusdc"),
+        )
+        .unwrap();
+        let mut tree = lib::merkle_tree::MerkleTreeWithHistory::new(20);
+        tree.insert(&Uint256::from(42u128)).unwrap();
+        let root = tree.get_last_root().to_string();
+        app.execute_contract(
+            Addr::unchecked("burner"),
+            contract_addr.clone(),
+            &ExecuteMsg::MigrateDeposit {
+                tier_id: 0,
+                proof: circuits
+                    .migrate
+                    .prove(&[root.clone(), "8".into(), "0".into(), "0".into()]),
+                root,
+                nullifier_hash: "8".into(),
+            },
+            &[],
+        )
+        .unwrap();
+        (app, contract_addr)
+    }
+
+    fn query_locked_balance(
+        app: &BasicApp<OsmosisSwap, Empty>,
+        contract_addr: &Addr,
+        addr: &str,
+        denom: &str,
+    ) -> Uint128 {
+        let key = MAP_ADDR_TO_LOCKED_BALANCES.key(Addr::unchecked(addr));
+        let locked_balances: Vec<AmountDenom> = app
+            .wrap()
+            .query_wasm_raw(contract_addr, key.to_vec())
+            .unwrap()
+            .map(|value| from_slice(&value).unwrap())
+            .unwrap_or_default();
+        locked_balances
+            .iter()
+            .find(|ad| ad.denom == Denom::Native(denom.into()))
+            .map(|ad| ad.amount)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_swap_through_astroport() {
+        let circuits = mock_circuits();
+        let (mut app, contract_addr) = setup_swap_app(&circuits);
+        let swap = |min_output_amount: u128| {
+            ExecuteMsg::Swap(SwapMsg {
+                routes: vec![OsmosisRoute {
+                    pool_id: "astro".into(),
+                    token_out_denom: "uosmo".into(),
+                }],
+                input_amount: Uint128::from(100u128),
+                input_denom: DenomUnvalidated::Native("uusdc".into()),
+                min_output_amount: Uint128::from(min_output_amount),
+                output_denom: DenomUnvalidated::Native("uosmo".into()),
+                max_slippage_bps: None,
+            })
+        };
+
+        // The pair doesn't take a minimum output, so the reply enforces it
+        let err = app
+            .execute_contract(
+                Addr::unchecked("burner"),
+                contract_addr.clone(),
+                &swap(201),
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            ContractError::InsufficientSwapOutput {}.to_string()
+        );
+        assert_eq!(
+            query_locked_balance(&app, &contract_addr, "burner", "uusdc"),
+            Uint128::from(100u128)
+        );
+
+        app.execute_contract(
+            Addr::unchecked("burner"),
+            contract_addr.clone(),
+            &swap(200),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            query_locked_balance(&app, &contract_addr, "burner", "uusdc"),
+            Uint128::zero()
+        );
+        assert_eq!(
+            query_locked_balance(&app, &contract_addr, "burner", "uosmo"),
+            Uint128::from(200u128)
+        );
+        let balance = app.wrap().query_balance(&contract_addr, "uosmo").unwrap();
+        assert_eq!(balance.amount, Uint128::from(200u128));
+        // The reply consumed the queued swap
+        assert!(app
+            .wrap()
+            .query_wasm_raw(contract_addr, SWAP_QUEUE.as_slice().to_vec())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_limit_orders() {
+        let circuits = mock_circuits();
+        let (mut app, contract_addr) = setup_swap_app(&circuits);
+        let burner = Addr::unchecked("burner");
+        let height = app.block_info().height;
+        let place = |limit_price: &str, keeper_tip_bps: u64, expires_at_height: u64| {
+            ExecuteMsg::PlaceLimitOrder(LimitOrderMsg {
+                routes: vec![OsmosisRoute {
+                    pool_id: "astro".into(),
+                    token_out_denom: "uosmo".into(),
+                }],
+                input_amount: Uint128::from(25u128),
+                input_denom: DenomUnvalidated::Native("uusdc".into()),
+                output_denom: DenomUnvalidated::Native("uosmo".into()),
+                limit_price: Decimal256::from_str(limit_price).unwrap(),
+                expires_at_height,
+                keeper_tip_bps: Some(keeper_tip_bps),
+            })
+        };
+
+        let err = app
+            .execute_contract(
+                burner.clone(),
+                contract_addr.clone(),
+                &place("1", 501, height + 10),
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            ContractError::InvalidKeeperTip {}.to_string()
+        );
+        let err = app
+            .execute_contract(
+                burner.clone(),
+                contract_addr.clone(),
+                &place("1", 0, height),
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            ContractError::OrderExpired {}.to_string()
+        );
+
+        // The pair pays 50 uosmo for 25 uusdc. Order 0 would only fill at that rate before
+        // its 2% tip, order 1 still fills after it. Order 3's limit overflows any amount
+        for order in [
+            place("2", 200, height + 10),
+            place("1.9", 200, height + 10),
+            place("1", 0, height + 10),
+            place(&Decimal256::MAX.to_string(), 0, height + 10),
+        ] {
+            app.execute_contract(burner.clone(), contract_addr.clone(), &order, &[])
+                .unwrap();
+        }
+        assert_eq!(
+            query_locked_balance(&app, &contract_addr, "burner", "uusdc"),
+            Uint128::zero()
+        );
+
+        // Keepers fill several orders at once, each swap settled by its own reply
+        let res = app
+            .execute_contract(
+                Addr::unchecked("keeper"),
+                contract_addr.clone(),
+                &ExecuteMsg::ExecuteOrders {
+                    order_ids: vec![0, 1, 2, 3, 4],
+                },
+                &[],
+            )
+            .unwrap();
+        assert!(res.has_event(
+            &Event::new("wasm")
+                .add_attribute("action", "execute_orders")
+                .add_attribute("from", "keeper")
+                .add_attribute("filled", "1,2")
+        ));
+        assert!(app
+            .wrap()
+            .query_wasm_raw(&contract_addr, SWAP_QUEUE.as_slice().to_vec())
+            .unwrap()
+            .is_none());
+        // Order 1 paid the keeper 1 of its 50 uosmo
+        let balance = app.wrap().query_balance("keeper", "uosmo").unwrap();
+        assert_eq!(balance.amount, Uint128::from(1u128));
+        assert_eq!(
+            query_locked_balance(&app, &contract_addr, "burner", "uosmo"),
+            Uint128::from(99u128)
+        );
+
+        // Only the owner can cancel, which returns the escrow
+        let cancel = ExecuteMsg::CancelLimitOrder { order_id: 0 };
+        let err = app
+            .execute_contract(
+                Addr::unchecked("other"),
+                contract_addr.clone(),
+                &cancel,
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            ContractError::Unauthorized {}.to_string()
+        );
+        app.execute_contract(burner.clone(), contract_addr.clone(), &cancel, &[])
+            .unwrap();
+        app.execute_contract(
+            burner,
+            contract_addr.clone(),
+            &ExecuteMsg::CancelLimitOrder { order_id: 3 },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            query_locked_balance(&app, &contract_addr, "burner", "uusdc"),
+            Uint128::from(50u128)
+        );
+        let res: LimitOrdersResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract_addr,
+                &QueryMsg::LimitOrders {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert!(res.orders.is_empty());
+    }
+
+    #[test]
+    fn test_dca_schedule() {
+        // One uusdc buys 2 uosmo over the TWAP window
+        let mut deps = mock_dependencies_with_twap("2");
+        ALLOWED_POOLS
+            .save(deps.as_mut().storage, "1".into(), &PoolVenue::Osmosis)
+            .unwrap();
+        let burner = Addr::unchecked("burner");
+        let uusdc = Denom::Native("uusdc".into());
+        add_amount_denom_to_locked_balance(
+            deps.as_mut().storage,
+            burner.clone(),
+            AmountDenom {
+                amount: Uint128::from(100u128),
+                denom: uusdc.clone(),
+            },
+        )
+        .unwrap();
+        let locked_uusdc = |deps: Deps| {
+            MAP_ADDR_TO_LOCKED_BALANCES
+                .load(deps.storage, Addr::unchecked("burner"))
+                .unwrap()
+                .iter()
+                .find(|ad| ad.denom == Denom::Native("uusdc".into()))
+                .map(|ad| ad.amount)
+                .unwrap_or_default()
+        };
+
+        // Three slices of 30 are escrowed up front
+        let mut env = mock_env();
+        let height = env.block.height;
+        execute_create_dca_schedule(
+            deps.as_mut(),
+            mock_info("burner", &[]),
+            env.clone(),
+            DcaScheduleMsg {
+                routes: vec![OsmosisRoute {
+                    pool_id: "1".into(),
+                    token_out_denom: "uosmo".into(),
+                }],
+                input_denom: DenomUnvalidated::Native("uusdc".into()),
+                output_denom: DenomUnvalidated::Native("uosmo".into()),
+                amount_per_slice: Uint128::from(30u128),
+                num_slices: 3,
+                interval: DcaInterval::Blocks(10),
+                max_slippage_bps: 100,
+            },
+        )
+        .unwrap();
+        assert_eq!(locked_uusdc(deps.as_ref()), Uint128::from(10u128));
+
+        // The first slice is due right away, with its minimum output 1% under the TWAP
+        let trigger = |deps: DepsMut, env: &Env| {
+            execute_trigger_dca(deps, mock_info("keeper", &[]), env.clone(), vec![0]).unwrap()
+        };
+        let res = trigger(deps.as_mut(), &env);
+        assert!(res.attributes.contains(&Attribute::new("triggered", "0")));
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                CosmosMsg::Custom(OsmosisSwap {
+                    _type: "osmosis/gamm/swap-exact-amount-in".into(),
+                    value: OsmosisSwapValue {
+                        routes: vec![OsmosisRoute {
+                            pool_id: "1".into(),
+                            token_out_denom: "uosmo".into(),
+                        }],
+                        sender: env.contract.address.to_string(),
+                        token_in: Coin::new(30, "uusdc"),
+                        token_out_min_amount: Uint128::from(59u128),
+                    },
+                }),
+                SWAP_REPLY_ID,
+            )]
+        );
+        let schedule = DCA_SCHEDULES.load(&deps.storage, 0).unwrap();
+        assert_eq!(schedule.remaining, Uint128::from(60u128));
+        assert_eq!(schedule.next_slice_at, height + 10);
+
+        // Nothing more until the interval has passed
+        env.block.height = height + 9;
+        let res = trigger(deps.as_mut(), &env);
+        assert!(res.attributes.contains(&Attribute::new("triggered", "")));
+        assert!(res.messages.is_empty());
+        env.block.height = height + 10;
+        let res = trigger(deps.as_mut(), &env);
+        assert!(res.attributes.contains(&Attribute::new("triggered", "0")));
+        assert_eq!(res.messages.len(), 1);
+        let schedule = DCA_SCHEDULES.load(&deps.storage, 0).unwrap();
+        assert_eq!(schedule.remaining, Uint128::from(30u128));
+        assert_eq!(schedule.next_slice_at, height + 20);
+
+        // Only the owner can cancel, which returns the unspent slice
+        let err =
+            execute_cancel_dca_schedule(deps.as_mut(), mock_info("other", &[]), 0).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute_cancel_dca_schedule(deps.as_mut(), mock_info("burner", &[]), 0).unwrap();
+        assert_eq!(locked_uusdc(deps.as_ref()), Uint128::from(40u128));
+        assert!(DCA_SCHEDULES.may_load(&deps.storage, 0).unwrap().is_none());
+        assert!(DCA_SCHEDULES_BY_OWNER
+            .may_load(&deps.storage, (burner, 0))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_get_swap_msgs_wraps_cw20() {
+        let mut deps = mock_dependencies();
+        ALLOWED_POOLS
+            .save(deps.as_mut().storage, "1".into(), &PoolVenue::Osmosis)
+            .unwrap();
+        CW20_WRAPPERS
+            .save(
+                deps.as_mut().storage,
+                Addr::unchecked("token"),
+                &Cw20Wrapper {
+                    converter: Addr::unchecked("converter"),
+                    native_denom: "factory/converter/token".into(),
+                },
+            )
+            .unwrap();
+        let routes = vec![OsmosisRoute {
+            pool_id: "1".into(),
+            token_out_denom: "uosmo".into(),
+        }];
+        let token = Denom::Cw20(Addr::unchecked("token"));
+        let uosmo = Denom::Native("uosmo".into());
+
+        // CW20 offer is converted before the Osmosis hop
+        let swap = get_swap_msgs(
+            &deps.storage,
+            &Addr::unchecked("contract"),
+            &routes,
+            &AmountDenom {
+                amount: Uint128::from(100u128),
+                denom: token.clone(),
+            },
+            Uint128::zero(),
+            &uosmo,
+        )
+        .unwrap();
+        assert_eq!(swap.msgs.len(), 2);
+        assert_eq!(
+            swap.msgs[0],
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "converter".into(),
+                    amount: Uint128::from(100u128),
+                    msg: to_binary(&Cw20ConverterHookMsg::Convert {}).unwrap(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+        assert_eq!(swap.output_denom, uosmo);
+        assert_eq!(swap.unwrap, None);
+
+        // CW20 output is read in the wrapped denom and unwrapped in the reply
+        let routes = vec![OsmosisRoute {
+            pool_id: "1".into(),
+            token_out_denom: "factory/converter/token".into(),
+        }];
+        let swap = get_swap_msgs(
+            &deps.storage,
+            &Addr::unchecked("contract"),
+            &routes,
+            &AmountDenom {
+                amount: Uint128::from(100u128),
+                denom: uosmo,
+            },
+            Uint128::zero(),
+            &token,
+        )
+        .unwrap();
+        assert_eq!(swap.msgs.len(), 1);
+        assert_eq!(
+            swap.output_denom,
+            Denom::Native("factory/converter/token".into())
+        );
+        assert_eq!(
+            swap.unwrap,
+            Some(Cw20Unwrap {
+                cw20_addr: Addr::unchecked("token"),
+                converter: Addr::unchecked("converter"),
+            })
+        );
+
+        // CW20s without a wrapper can't be swapped on Osmosis
+        CW20_WRAPPERS.remove(deps.as_mut().storage, Addr::unchecked("token"));
+        let res = get_swap_msgs(
+            &deps.storage,
+            &Addr::unchecked("contract"),
+            &routes,
+            &AmountDenom {
+                amount: Uint128::from(100u128),
+                denom: Denom::Native("uosmo".into()),
+            },
+            Uint128::zero(),
+            &token,
+        );
+        assert!(res.is_err());
     }
 }
@@ -34,4 +34,43 @@ pub enum ContractError {
 
     #[error("Insufficient funds in locked balance")]
     InsufficientLockedBalance {},
+
+    #[error("Route mixes pools from different venues")]
+    MixedVenues {},
+
+    #[error("Swap output below minimum")]
+    InsufficientSwapOutput {},
+
+    #[error("Invalid slippage")]
+    InvalidSlippage {},
+
+    #[error("No TWAP history for pool")]
+    MissingTwap { pool_id: String },
+
+    #[error("Invalid keeper tip")]
+    InvalidKeeperTip {},
+
+    #[error("Order expired")]
+    OrderExpired {},
+
+    #[error("Unknown order")]
+    UnknownOrder { id: u64 },
+
+    #[error("Invalid DCA schedule")]
+    InvalidDcaSchedule {},
+
+    #[error("Unknown DCA schedule")]
+    UnknownDcaSchedule { id: u64 },
+
+    #[error("Unknown tier")]
+    UnknownTier { id: u32 },
+
+    #[error("Deposit can't be reclaimed yet")]
+    ReclaimTooEarly {},
+
+    #[error("Root is too recent")]
+    RootTooRecent {},
+
+    #[error("Anonymity set is too small")]
+    AnonymitySetTooSmall {},
 }
@@ -2,6 +2,11 @@ pub mod contract;
 mod error;
 pub mod helpers;
 pub mod msg;
+pub mod osmosis;
 pub mod state;
+pub mod venue;
+
+#[cfg(test)]
+mod testing;
 
 pub use crate::error::ContractError;
@@ -1,8 +1,11 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Uint128};
+use cosmwasm_std::{Api, Coin, CustomMsg, Decimal, Decimal256, StdResult, Uint128};
+use cw20::Cw20ReceiveMsg;
 use lib::msg::CircomProof;
 
-use crate::state::Denom;
+use crate::state::{
+    DcaInterval, DcaSchedule, Denom, LimitOrder, MigrationPolicy, PoolVenue, RootExpiry, Tier,
+};
 
 #[cw_serde]
 pub enum DenomUnvalidated {
@@ -10,61 +13,324 @@ pub enum DenomUnvalidated {
     Cw20(String),
 }
 
+impl DenomUnvalidated {
+    pub fn validate(self, api: &dyn Api) -> StdResult<Denom> {
+        match self {
+            DenomUnvalidated::Native(denom) => Ok(Denom::Native(denom)),
+            DenomUnvalidated::Cw20(addr) => Ok(Denom::Cw20(api.addr_validate(&addr)?)),
+        }
+    }
+}
+
 #[cw_serde]
-pub struct InstantiateMsg {
-    pub amount: Uint128,
+pub enum PoolVenueUnvalidated {
+    Osmosis,
+    Astroport { pair_addr: String },
+}
+
+impl PoolVenueUnvalidated {
+    pub fn validate(self, api: &dyn Api) -> StdResult<PoolVenue> {
+        match self {
+            PoolVenueUnvalidated::Osmosis => Ok(PoolVenue::Osmosis),
+            PoolVenueUnvalidated::Astroport { pair_addr } => Ok(PoolVenue::Astroport {
+                pair_addr: api.addr_validate(&pair_addr)?,
+            }),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct AllowedPool {
+    // Pool id as used in `OsmosisRoute::pool_id`
+    pub pool_id: String,
+    pub venue: PoolVenueUnvalidated,
+}
+
+#[cw_serde]
+pub struct Cw20WrapperMsg {
+    pub cw20_addr: String,
+    pub converter: String,
+    pub native_denom: String,
+}
+
+#[cw_serde]
+pub struct TierMsg {
     pub denom: DenomUnvalidated,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    // Tier ids are indexes into this list
+    pub tiers: Vec<TierMsg>,
 
     pub pool_admin: Option<String>,
-    pub allowed_pools: Vec<String>,
+    pub allowed_pools: Vec<AllowedPool>,
+    pub cw20_wrappers: Vec<Cw20WrapperMsg>,
 
     pub vk_deposit: String,
     pub vk_swap_deposit: String,
     pub vk_withdraw: String,
+    pub vk_reclaim: String,
+    pub reclaim_delay_blocks: u64,
+    pub migration_policy: Option<MigrationPolicy>,
+    // Defaults to the last 100 roots
+    pub root_expiry: Option<RootExpiry>,
+}
+
+#[cw_serde]
+pub struct DepositMsg {
+    pub tier_id: u32,
+    pub proof: CircomProof,
+    pub credential: String,
+    pub withdraw_addr: String,
+}
+
+#[cw_serde]
+pub struct BatchDeposit {
+    pub proof: CircomProof,
+    pub credential: String,
+    pub withdraw_addr: String,
+}
+
+#[cw_serde]
+pub struct SwapMsg {
+    pub routes: Vec<OsmosisRoute>,
+    pub input_amount: Uint128,
+    pub input_denom: DenomUnvalidated,
+    pub min_output_amount: Uint128,
+    pub output_denom: DenomUnvalidated,
+    // If set, the minimum output is raised to the TWAP-implied output less this slippage
+    pub max_slippage_bps: Option<u64>,
+}
+
+#[cw_serde]
+pub struct LimitOrderMsg {
+    pub routes: Vec<OsmosisRoute>,
+    pub input_amount: Uint128,
+    pub input_denom: DenomUnvalidated,
+    pub output_denom: DenomUnvalidated,
+    pub limit_price: Decimal256,
+    pub expires_at_height: u64,
+    // Share of the output paid to the filling keeper, at most 500 bps. The limit
+    // price holds net of it
+    pub keeper_tip_bps: Option<u64>,
+}
+
+#[cw_serde]
+pub struct DcaScheduleMsg {
+    pub routes: Vec<OsmosisRoute>,
+    pub input_denom: DenomUnvalidated,
+    pub output_denom: DenomUnvalidated,
+    pub amount_per_slice: Uint128,
+    pub num_slices: u64,
+    pub interval: DcaInterval,
+    // Each slice's minimum output is the TWAP-implied output less this slippage
+    pub max_slippage_bps: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct MigrateMsg {
+    // Replace the verifying keys, e.g. after the circuits changed
+    pub vk_deposit: Option<String>,
+    pub vk_swap_deposit: Option<String>,
+    pub vk_withdraw: Option<String>,
+    // Required when migrating from a contract without reclaims
+    pub vk_reclaim: Option<String>,
+    pub reclaim_delay_blocks: Option<u64>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     Deposit {
+        tier_id: u32,
         proof: CircomProof,
         credential: String,
         withdraw_addr: String,
     },
+    // Deposit several notes of one tier, with funds covering all of them
+    DepositBatch {
+        tier_id: u32,
+        deposits: Vec<BatchDeposit>,
+    },
+    // Deposit into a CW20 pool, sent by the token contract with a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
     MigrateDeposit {
+        tier_id: u32,
         proof: CircomProof,
         root: String,
         nullifier_hash: String,
     },
-    Swap {
-        routes: Vec<OsmosisRoute>,
-        input_amount: Uint128,
-        input_denom: DenomUnvalidated,
-        min_output_amount: Uint128,
-        output_denom: DenomUnvalidated,
+    // Refund a deposit that was never migrated to its withdraw address,
+    // once it is `reclaim_delay_blocks` old
+    Reclaim {
+        tier_id: u32,
+        proof: CircomProof,
+        credential: String,
+        nullifier_hash: String,
     },
+    Swap(SwapMsg),
     Withdraw {
         proof: CircomProof,
         withdraw_addr: String,
-        burner_addr: String
+        burner_addr: String,
     },
     UpdateAllowedPools {
-        pools: Vec<String>,
+        pools: Vec<AllowedPool>,
+    },
+    UpdateMigrationPolicy {
+        policy: MigrationPolicy,
+    },
+    // Replaces the CW20 wrappers Osmosis swaps convert through
+    UpdateCw20Wrappers {
+        wrappers: Vec<Cw20WrapperMsg>,
+    },
+    // Escrow part of the locked balance until a keeper can fill it at `limit_price` or better
+    PlaceLimitOrder(LimitOrderMsg),
+    CancelLimitOrder {
+        order_id: u64,
+    },
+    // Permissionless. Fills every listed order whose estimated output meets its limit.
+    // Fails if any filled order's swap misses its limit
+    ExecuteOrders {
+        order_ids: Vec<u64>,
+    },
+    // Escrow `amount_per_slice * num_slices` to be swapped one slice per interval
+    CreateDcaSchedule(DcaScheduleMsg),
+    CancelDcaSchedule {
+        schedule_id: u64,
+    },
+    // Permissionless. Swaps one slice of every listed schedule that is due. Fails if
+    // any slice misses its minimum output
+    TriggerDca {
+        schedule_ids: Vec<u64>,
     },
 }
 
 #[cw_serde]
-pub struct OwnershipResponse {
-    pub amount: Uint128,
-    pub denom: Denom,
-    pub counter: u32,
+pub enum ReceiveMsg {
+    Deposit(DepositMsg),
+    DepositBatch {
+        tier_id: u32,
+        deposits: Vec<BatchDeposit>,
+    },
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
-    // Get ownership and counter
-    #[returns(OwnershipResponse)]
-    GetOwnership { deposit_credential_hash: String },
+    // Deposit tiers in id order, with the number of deposits in each
+    #[returns(TiersResponse)]
+    Tiers {},
+    // Where a credential was inserted, for building Merkle paths
+    #[returns(DepositResponse)]
+    Deposit { tier_id: u32, credential: String },
+    // How old a root is and how many deposits it could be hiding
+    #[returns(RootInfoResponse)]
+    RootInfo { tier_id: u32, root: String },
+    // Check a Merkle path the way the migrate circuit does, before proving with it
+    #[returns(VerifyPathResponse)]
+    VerifyPath {
+        leaf: String,
+        root: String,
+        path_elements: Vec<String>,
+        path_indices: Vec<String>,
+    },
+    // Quote a swap from a burner's locked balance, with the same route checks as `Swap`
+    #[returns(SimulateSwapResponse)]
+    SimulateSwap {
+        burner_addr: String,
+        routes: Vec<OsmosisRoute>,
+        input_amount: Uint128,
+        input_denom: DenomUnvalidated,
+        output_denom: DenomUnvalidated,
+    },
+    #[returns(LimitOrderResponse)]
+    LimitOrder { order_id: u64 },
+    // Open limit orders in id order, for keepers
+    #[returns(LimitOrdersResponse)]
+    LimitOrders {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(DcaScheduleResponse)]
+    DcaSchedule { schedule_id: u64 },
+    // All schedules, or only those of `owner`, in id order
+    #[returns(DcaSchedulesResponse)]
+    DcaSchedules {
+        owner: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct DepositResponse {
+    // Tree epoch the leaf index refers to
+    pub epoch: u32,
+    pub leaf_index: u32,
+    pub height: u64,
+    pub withdraw_addr: String,
+}
+
+#[cw_serde]
+pub struct RootInfoResponse {
+    pub height: u64,
+    // Deposits a migration against this root could have come from
+    pub anonymity_set: u32,
+    pub epoch: u32,
+}
+
+#[cw_serde]
+pub struct VerifyPathResponse {
+    pub valid: bool,
+}
+
+#[cw_serde]
+pub struct TierResponse {
+    pub tier_id: u32,
+    pub tier: Tier,
+    // Epoch taking new deposits
+    pub epoch: u32,
+    pub deposit_count: u32,
+    // Deposits since spent by a migration or a reclaim
+    pub withdrawal_count: u32,
+}
+
+#[cw_serde]
+pub struct TiersResponse {
+    pub tiers: Vec<TierResponse>,
+}
+
+#[cw_serde]
+pub struct DcaScheduleResponse {
+    pub schedule_id: u64,
+    pub schedule: DcaSchedule,
+}
+
+#[cw_serde]
+pub struct DcaSchedulesResponse {
+    pub schedules: Vec<DcaScheduleResponse>,
+}
+
+#[cw_serde]
+pub struct LimitOrderResponse {
+    pub order_id: u64,
+    pub order: LimitOrder,
+}
+
+#[cw_serde]
+pub struct LimitOrdersResponse {
+    pub orders: Vec<LimitOrderResponse>,
+}
+
+#[cw_serde]
+pub struct SimulateSwapResponse {
+    pub output_amount: Uint128,
+    // Shortfall of the output relative to swapping at the current spot prices
+    pub price_impact: Decimal256,
+    pub locked_balance_sufficient: bool,
 }
 
 // MARK: Osmosis Messages
@@ -89,3 +355,64 @@ pub struct OsmosisSwap {
     pub _type: String,
     pub value: OsmosisSwapValue,
 }
+
+impl CustomMsg for OsmosisSwap {}
+
+// MARK: Astroport Messages
+
+#[cw_serde]
+pub enum AstroportAssetInfo {
+    Token { contract_addr: String },
+    NativeToken { denom: String },
+}
+
+#[cw_serde]
+pub struct AstroportAsset {
+    pub info: AstroportAssetInfo,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub enum AstroportPairExecuteMsg {
+    Swap {
+        offer_asset: AstroportAsset,
+        belief_price: Option<Decimal>,
+        max_spread: Option<Decimal>,
+        to: Option<String>,
+    },
+}
+
+#[cw_serde]
+pub enum AstroportCw20HookMsg {
+    Swap {
+        belief_price: Option<Decimal>,
+        max_spread: Option<Decimal>,
+        to: Option<String>,
+    },
+}
+
+#[cw_serde]
+pub enum AstroportPairQueryMsg {
+    Simulation { offer_asset: AstroportAsset },
+}
+
+#[cw_serde]
+pub struct AstroportSimulationResponse {
+    pub return_amount: Uint128,
+    pub spread_amount: Uint128,
+    pub commission_amount: Uint128,
+}
+
+// MARK: CW20 converter messages
+
+// Sent with the CW20 to mint the wrapper's native denom
+#[cw_serde]
+pub enum Cw20ConverterHookMsg {
+    Convert {},
+}
+
+// Sent with the native denom as funds to redeem the CW20
+#[cw_serde]
+pub enum Cw20ConverterExecuteMsg {
+    Convert {},
+}
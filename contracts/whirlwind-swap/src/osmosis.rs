@@ -0,0 +1,193 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    Binary, Coin, Decimal256, QuerierWrapper, QueryRequest, StdError, StdResult, Timestamp, Uint128,
+};
+use serde::Deserialize;
+
+use crate::msg::OsmosisRoute;
+
+// Osmosis answers whitelisted Stargate queries with the JSON encoding of the
+// response, but still expects the request itself as protobuf. The handful of
+// requests we need are small enough to encode by hand.
+
+fn encode_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn encode_key(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    encode_varint(buf, ((field as u64) << 3) | wire_type as u64);
+}
+
+fn encode_uint64(buf: &mut Vec<u8>, field: u32, value: u64) {
+    if value == 0 {
+        return;
+    }
+    encode_key(buf, field, 0);
+    encode_varint(buf, value);
+}
+
+fn encode_bytes(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+    encode_key(buf, field, 2);
+    encode_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn encode_string(buf: &mut Vec<u8>, field: u32, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    encode_bytes(buf, field, value.as_bytes());
+}
+
+fn encode_timestamp(buf: &mut Vec<u8>, field: u32, value: Timestamp) {
+    let mut ts = vec![];
+    encode_uint64(&mut ts, 1, value.seconds());
+    encode_uint64(&mut ts, 2, value.subsec_nanos());
+    encode_bytes(buf, field, &ts);
+}
+
+fn parse_decimal(value: &str) -> StdResult<Decimal256> {
+    Decimal256::from_str(value)
+}
+
+// MARK: TWAP
+
+const ARITHMETIC_TWAP_TO_NOW_PATH: &str = "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow";
+
+#[derive(Deserialize)]
+struct ArithmeticTwapToNowResponse {
+    arithmetic_twap: String,
+}
+
+/// Arithmetic TWAP of `base_asset` denominated in `quote_asset` from `start_time` until now,
+/// i.e. the average amount of `quote_asset` received for one unit of `base_asset`.
+pub fn query_arithmetic_twap_to_now(
+    querier: &QuerierWrapper,
+    pool_id: u64,
+    base_asset: &str,
+    quote_asset: &str,
+    start_time: Timestamp,
+) -> StdResult<Decimal256> {
+    let mut data = vec![];
+    encode_uint64(&mut data, 1, pool_id);
+    encode_string(&mut data, 2, base_asset);
+    encode_string(&mut data, 3, quote_asset);
+    encode_timestamp(&mut data, 4, start_time);
+
+    let res: ArithmeticTwapToNowResponse = querier.query(&QueryRequest::Stargate {
+        path: ARITHMETIC_TWAP_TO_NOW_PATH.to_string(),
+        data: Binary::from(data),
+    })?;
+    parse_decimal(&res.arithmetic_twap)
+}
+
+// MARK: Pool manager
+
+const ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH: &str =
+    "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountIn";
+const SPOT_PRICE_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/SpotPrice";
+
+#[derive(Deserialize)]
+struct EstimateSwapExactAmountInResponse {
+    token_out_amount: String,
+}
+
+#[derive(Deserialize)]
+struct SpotPriceResponse {
+    spot_price: String,
+}
+
+/// Output the pool manager expects for swapping `token_in` along `routes` at current reserves.
+pub fn query_estimate_swap_exact_amount_in(
+    querier: &QuerierWrapper,
+    token_in: &Coin,
+    routes: &[OsmosisRoute],
+) -> StdResult<Uint128> {
+    let mut data = vec![];
+    // `pool_id` (field 2) is deprecated in favour of the routes, but still set for older nodes
+    if let Some(route) = routes.first() {
+        encode_uint64(&mut data, 2, parse_pool_id(&route.pool_id)?);
+    }
+    encode_string(&mut data, 3, &token_in.to_string());
+    for route in routes.iter() {
+        let mut r = vec![];
+        encode_uint64(&mut r, 1, parse_pool_id(&route.pool_id)?);
+        encode_string(&mut r, 2, &route.token_out_denom);
+        encode_bytes(&mut data, 4, &r);
+    }
+
+    let res: EstimateSwapExactAmountInResponse = querier.query(&QueryRequest::Stargate {
+        path: ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH.to_string(),
+        data: Binary::from(data),
+    })?;
+    Uint128::from_str(&res.token_out_amount)
+}
+
+/// Spot price of `base_asset_denom` denominated in `quote_asset_denom`.
+pub fn query_spot_price(
+    querier: &QuerierWrapper,
+    pool_id: u64,
+    base_asset_denom: &str,
+    quote_asset_denom: &str,
+) -> StdResult<Decimal256> {
+    let mut data = vec![];
+    encode_uint64(&mut data, 1, pool_id);
+    encode_string(&mut data, 2, base_asset_denom);
+    encode_string(&mut data, 3, quote_asset_denom);
+
+    let res: SpotPriceResponse = querier.query(&QueryRequest::Stargate {
+        path: SPOT_PRICE_PATH.to_string(),
+        data: Binary::from(data),
+    })?;
+    parse_decimal(&res.spot_price)
+}
+
+pub fn parse_pool_id(pool_id: &str) -> StdResult<u64> {
+    u64::from_str(pool_id)
+        .map_err(|_| StdError::generic_err(format!("Invalid Osmosis pool id: {}", pool_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_varint() {
+        let mut buf = vec![];
+        encode_varint(&mut buf, 1);
+        assert_eq!(buf, vec![0x01]);
+
+        let mut buf = vec![];
+        encode_varint(&mut buf, 300);
+        assert_eq!(buf, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_encode_fields() {
+        let mut buf = vec![];
+        encode_uint64(&mut buf, 1, 150);
+        encode_string(&mut buf, 2, "uosmo");
+        assert_eq!(
+            buf,
+            vec![0x08, 0x96, 0x01, 0x12, 0x05, b'u', b'o', b's', b'm', b'o']
+        );
+
+        // Default values are omitted, as in proto3
+        let mut buf = vec![];
+        encode_uint64(&mut buf, 1, 0);
+        encode_string(&mut buf, 2, "");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_encode_timestamp() {
+        let mut buf = vec![];
+        encode_timestamp(&mut buf, 4, Timestamp::from_nanos(1_000_000_005));
+        assert_eq!(buf, vec![0x22, 0x04, 0x08, 0x01, 0x10, 0x05]);
+    }
+}
@@ -1,9 +1,12 @@
 use cw_storage_plus::{Item, Map};
-use cosmwasm_std::{Uint128, Addr, Uint256};
-use lib::merkle_tree::MerkleTreeWithHistory;
+use cosmwasm_std::{Uint128, Addr, Uint256, Decimal256};
+use lib::merkle_tree::{MerkleTreeWithHistory, DEFAULT_ROOT_HISTORY_SIZE};
+use lib::storage_tree::MerkleTreeStore;
 use lib::verifier::Verifier;
 use cosmwasm_schema::{cw_serde};
 
+use crate::msg::OsmosisRoute;
+
 #[cw_serde]
 pub enum Denom {
     Native(String),
@@ -16,33 +19,202 @@ pub struct AmountDenom {
     pub denom: Denom
 }
 
-pub const DEPOSIT_AMOUNT: Item<Uint128> = Item::new("deposit_amount");
-pub const DEPOSIT_DENOM: Item<Denom> = Item::new("deposit_denom");
+// Fixed-amount deposit tiers, keyed by tier id. Each tier has its own
+// commitment tree and nullifier namespace
+pub const TIERS: Map<u32, Tier> = Map::new("tiers");
+
+#[cw_serde]
+pub struct Tier {
+    pub denom: Denom,
+    pub amount: Uint128,
+}
 
 // Verifiers
 pub const DEPOSIT_VERIFIER: Item<Verifier> = Item::new("verifier");
 pub const MIGRATE_VERIFIER: Item<Verifier> = Item::new("migrate_verifier");
 pub const WITHDRAW_VERIFIER: Item<Verifier> = Item::new("withdraw_verifier");
+pub const RECLAIM_VERIFIER: Item<Verifier> = Item::new("reclaim_verifier");
+// Migrate verifier from before deposit tiers. Proves notes of the single pool imported
+// as epoch 0 of tier 0, whose leaves have no tier id
+pub const LEGACY_MIGRATE_VERIFIER: Item<Verifier> = Item::new("legacy_migrate_verifier");
+
+// Blocks after which an unmigrated deposit can be reclaimed to its withdraw address
+pub const RECLAIM_DELAY_BLOCKS: Item<u64> = Item::new("reclaim_delay_blocks");
 
 // Deposit
-pub const COMMITMENTS: Item<MerkleTreeWithHistory> = Item::new("commitments");
-pub const NULLIFIERS: Map<String, bool> = Map::new("nullifier_hashes");
+// Commitment trees, keyed by tree id. A tier fills one tree per epoch
+pub const COMMITMENTS: MerkleTreeStore = MerkleTreeStore::new(
+    "commitments_meta",
+    "commitments_filled_subtrees",
+    "commitments_roots",
+    "commitments_root_slots",
+);
+// Epoch of each tier that takes new deposits
+pub const CURRENT_EPOCHS: Map<u32, u32> = Map::new("tier_current_epochs");
+// Tree id of each (tier id, epoch)
+pub const EPOCH_TREES: Map<(u32, u32), u32> = Map::new("tier_epoch_trees");
+pub const NEXT_TREE_ID: Item<u32> = Item::new("next_tree_id");
+// Single pool from before deposit tiers, moved into tier 0 by `migrate`
+pub const LEGACY_DEPOSIT_AMOUNT: Item<Uint128> = Item::new("deposit_amount");
+pub const LEGACY_DEPOSIT_DENOM: Item<Denom> = Item::new("deposit_denom");
+pub const LEGACY_COMMITMENT_TREE: Item<MerkleTreeWithHistory> = Item::new("commitments");
+pub const LEGACY_NULLIFIERS: Map<String, bool> = Map::new("nullifier_hashes");
+pub const NULLIFIERS: Map<(u32, String), bool> = Map::new("tier_nullifier_hashes");
+// Notes of each tier spent by a migration or a reclaim
+pub const WITHDRAWAL_COUNTS: Map<u32, u32> = Map::new("tier_withdrawal_counts");
+// Deposited credentials of each tier
+pub const CREDENTIALS: Map<(u32, String), DepositInfo> = Map::new("tier_credentials");
+
+// When each root of a tier was created, keyed by (tier id, root)
+pub const ROOT_INFO: Map<(u32, String), RootInfo> = Map::new("tier_root_info");
+
+#[cw_serde]
+pub struct RootInfo {
+    pub height: u64,
+    // Leaves in the tree when the root was made
+    pub leaves: u32,
+    pub epoch: u32,
+}
+
+// Guards against migrations that trivially link depositor and burner
+#[cw_serde]
+#[derive(Default)]
+pub struct MigrationPolicy {
+    // Blocks the referenced root must have existed for
+    pub min_root_age_blocks: Option<u64>,
+    // Leaves the tree must have had when the referenced root was made
+    pub min_anonymity_set: Option<u32>,
+}
+
+pub const MIGRATION_POLICY: Item<MigrationPolicy> = Item::new("migration_policy");
+
+// How long a root stays valid for migrations
+#[cw_serde]
+pub enum RootExpiry {
+    // The last `size` roots of the tree
+    History { size: u32 },
+    // Any root made in the last `blocks` blocks, looked up in `ROOT_INFO`
+    Blocks { blocks: u64 },
+}
+
+impl Default for RootExpiry {
+    fn default() -> Self {
+        RootExpiry::History {
+            size: DEFAULT_ROOT_HISTORY_SIZE,
+        }
+    }
+}
+
+pub const ROOT_EXPIRY: Item<RootExpiry> = Item::new("root_expiry");
+
+#[cw_serde]
+pub struct DepositInfo {
+    pub epoch: u32,
+    pub leaf_index: u32,
+    // Block height of the deposit
+    pub height: u64,
+    // Where a reclaim pays out
+    pub withdraw_addr: Addr,
+}
 
 // Pools need allowance list to prevent minting a new coin in Frontier
 // and exiting liquidity anonymously
 pub const POOL_ADMIN: Item<Addr> = Item::new("admin");
-pub const ALLOWED_POOLS: Item<Vec<String>> = Item::new("allowed_pools"); 
+pub const ALLOWED_POOLS: Map<String, PoolVenue> = Map::new("allowed_pool_venues");
+// Osmosis pool ids allowed before pool venues, moved over by `migrate`
+pub const LEGACY_ALLOWED_POOLS: Item<Vec<String>> = Item::new("allowed_pools");
+
+#[cw_serde]
+pub enum PoolVenue {
+    // Osmosis pool manager pool
+    Osmosis,
+    // Astroport-style pair contract
+    Astroport { pair_addr: Addr },
+}
+
+// CW20s that Osmosis pools trade through a native wrapper, e.g. a tokenfactory
+// converter. Keyed by CW20 address
+pub const CW20_WRAPPERS: Map<Addr, Cw20Wrapper> = Map::new("cw20_wrappers");
+
+#[cw_serde]
+pub struct Cw20Wrapper {
+    // Mints `native_denom` 1:1 for the CW20, and redeems it back 1:1
+    pub converter: Addr,
+    pub native_denom: String,
+}
 
 // Locked balances and previous nullifiers
 pub const MAP_ADDR_TO_PREVIOUS_NULLIFIER: Map<Addr, Uint256> = Map::new("map_addr_to_previous_nullifier");
 pub const MAP_ADDR_TO_LOCKED_BALANCES: Map<Addr, Vec<AmountDenom>> = Map::new("map_addr_to_locked_balance");
 
+#[cw_serde]
+pub struct KeeperTip {
+    pub keeper: Addr,
+    pub bps: u64,
+}
+
 #[cw_serde]
 pub struct SwapContext {
     pub swapper_addr: Addr,
     pub output_balance_before_swap: Uint128,
     pub output_denom: Denom,
+    // Checked in the reply, for venues that can't enforce it themselves
+    pub min_output_amount: Uint128,
+    pub keeper_tip: Option<KeeperTip>,
+    // Set when `output_denom` is a wrapped CW20 to be redeemed before crediting
+    pub unwrap: Option<Cw20Unwrap>,
+}
+
+#[cw_serde]
+pub struct Cw20Unwrap {
+    pub cw20_addr: Addr,
+    pub converter: Addr,
+}
+
+// Reply context. Swaps are queued in dispatch order, as each swap's reply
+// runs before the next swap is executed
+pub const SWAP_QUEUE: Item<Vec<SwapContext>> = Item::new("swap_queue");
+
+#[cw_serde]
+pub struct LimitOrder {
+    pub owner: Addr,
+    pub routes: Vec<OsmosisRoute>,
+    // Escrowed from the owner's locked balance
+    pub input: AmountDenom,
+    pub output_denom: Denom,
+    // Output per unit of input the order fills at or better
+    pub limit_price: Decimal256,
+    pub expires_at_height: u64,
+    // Share of the output paid to the keeper that fills the order
+    pub keeper_tip_bps: u64,
+}
+
+// Limit orders
+pub const LIMIT_ORDERS: Map<u64, LimitOrder> = Map::new("limit_orders");
+pub const NEXT_LIMIT_ORDER_ID: Item<u64> = Item::new("next_limit_order_id");
+
+#[cw_serde]
+pub enum DcaInterval {
+    Blocks(u64),
+    Seconds(u64),
+}
+
+#[cw_serde]
+pub struct DcaSchedule {
+    pub owner: Addr,
+    pub routes: Vec<OsmosisRoute>,
+    pub input_denom: Denom,
+    pub output_denom: Denom,
+    pub amount_per_slice: Uint128,
+    // Escrowed input that hasn't been swapped yet
+    pub remaining: Uint128,
+    pub interval: DcaInterval,
+    // Block height or time in seconds, following `interval`, at which the next slice is due
+    pub next_slice_at: u64,
+    pub max_slippage_bps: u64,
 }
 
-// Reply context
-pub const SWAP_CTX: Item<SwapContext> = Item::new("swap_ctx");
+// DCA schedules
+pub const DCA_SCHEDULES: Map<u64, DcaSchedule> = Map::new("dca_schedules");
+pub const DCA_SCHEDULES_BY_OWNER: Map<(Addr, u64), ()> = Map::new("dca_schedules_by_owner");
+pub const NEXT_DCA_SCHEDULE_ID: Item<u64> = Item::new("next_dca_schedule_id");
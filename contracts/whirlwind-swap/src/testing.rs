@@ -0,0 +1,236 @@
+// Mocks shared by the tests of several modules
+use std::str::FromStr;
+
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{create_random_proof, generate_random_parameters, ProvingKey};
+use ark_relations::lc;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable};
+use ark_std::test_rng;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    coins, from_slice, to_binary, Addr, BankMsg, Binary, ContractResult, Deps, DepsMut, Empty, Env,
+    MessageInfo, OwnedDeps, Querier, QuerierResult, QueryRequest, Response, StdError, StdResult,
+    SystemResult, Uint128, Uint256,
+};
+use cw_multi_test::{BasicApp, ContractWrapper, Executor};
+use cw_storage_plus::Item;
+use lib::msg::CircomProof;
+use lib::verifier::VerifyingKeyJson;
+use serde_json::json;
+
+use crate::msg::{
+    AstroportPairExecuteMsg, AstroportPairQueryMsg, AstroportSimulationResponse, OsmosisSwap,
+};
+
+// MARK: Groth16
+
+// Groth16 keys and proofs made for a circuit that only exposes its public signals. A
+// proof then verifies for exactly the signals it was made with, which checks what the
+// contract passes to its verifiers without the circom toolchain
+
+struct PublicSignalsCircuit {
+    signals: Vec<Fr>,
+}
+
+impl ConstraintSynthesizer<Fr> for PublicSignalsCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        for signal in self.signals {
+            let input = cs.new_input_variable(|| Ok(signal))?;
+            let witness = cs.new_witness_variable(|| Ok(signal))?;
+            cs.enforce_constraint(lc!() + input, lc!() + Variable::One, lc!() + witness)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct MockCircuit {
+    pk: ProvingKey<Bn254>,
+}
+
+impl MockCircuit {
+    pub fn new(public_signals: usize) -> Self {
+        let circuit = PublicSignalsCircuit {
+            signals: vec![Fr::from(0u64); public_signals],
+        };
+        let pk = generate_random_parameters::<Bn254, _, _>(circuit, &mut test_rng()).unwrap();
+        Self { pk }
+    }
+
+    /// Verification key in snarkjs' format
+    pub fn vk_json(&self) -> String {
+        let vk = &self.pk.vk;
+        let alphabeta = Bn254::pairing(vk.alpha_g1, vk.beta_g2);
+        let fq6_to_strings = |c: &<Bn254 as PairingEngine>::Fqk| {
+            [c.c0, c.c1]
+                .iter()
+                .map(|fq6| {
+                    [fq6.c0, fq6.c1, fq6.c2]
+                        .iter()
+                        .map(|fq2| vec![fq_to_string(&fq2.c0), fq_to_string(&fq2.c1)])
+                        .collect()
+                })
+                .collect()
+        };
+        let vk_json = VerifyingKeyJson {
+            ic: vk.gamma_abc_g1.iter().map(g1_to_strings).collect(),
+            vk_alpha_1: g1_to_strings(&vk.alpha_g1),
+            vk_beta_2: g2_to_strings(&vk.beta_g2),
+            vk_gamma_2: g2_to_strings(&vk.gamma_g2),
+            vk_delta_2: g2_to_strings(&vk.delta_g2),
+            vk_alphabeta_12: fq6_to_strings(&alphabeta),
+        };
+        serde_json::to_string(&vk_json).unwrap()
+    }
+
+    pub fn prove(&self, public_signals: &[String]) -> CircomProof {
+        let circuit = PublicSignalsCircuit {
+            signals: public_signals
+                .iter()
+                .map(|signal| Fr::from_str(signal).unwrap())
+                .collect(),
+        };
+        let proof = create_random_proof(circuit, &self.pk, &mut test_rng()).unwrap();
+        CircomProof {
+            pi_a: g1_to_strings(&proof.a),
+            pi_b: g2_to_strings(&proof.b),
+            pi_c: g1_to_strings(&proof.c),
+            protocol: "groth16".into(),
+            curve: "bn128".into(),
+        }
+    }
+}
+
+fn fq_to_string(fq: &Fq) -> String {
+    let bytes: [u8; 32] = fq.into_repr().to_bytes_le().try_into().unwrap();
+    Uint256::from_le_bytes(bytes).to_string()
+}
+
+fn g1_to_strings(point: &G1Affine) -> Vec<String> {
+    vec![fq_to_string(&point.x), fq_to_string(&point.y), "1".into()]
+}
+
+fn g2_to_strings(point: &G2Affine) -> Vec<Vec<String>> {
+    vec![
+        vec![fq_to_string(&point.x.c0), fq_to_string(&point.x.c1)],
+        vec![fq_to_string(&point.y.c0), fq_to_string(&point.y.c1)],
+        vec!["1".into(), "0".into()],
+    ]
+}
+
+// MARK: Osmosis TWAP
+
+// `MockQuerier` answering every Osmosis TWAP query with the same price
+pub struct TwapQuerier {
+    base: MockQuerier,
+    twap: String,
+}
+
+impl Querier for TwapQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice::<QueryRequest<Empty>>(bin_request) {
+            Ok(QueryRequest::Stargate { path, .. })
+                if path == "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow" =>
+            {
+                let res = json!({ "arithmetic_twap": self.twap });
+                SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+            }
+            _ => self.base.raw_query(bin_request),
+        }
+    }
+}
+
+pub fn mock_dependencies_with_twap(twap: &str) -> OwnedDeps<MockStorage, MockApi, TwapQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: TwapQuerier {
+            base: MockQuerier::default(),
+            twap: twap.into(),
+        },
+        custom_query_type: Default::default(),
+    }
+}
+
+// MARK: Astroport
+
+// Mock Astroport pair paying out a fixed 2:1 rate in `ask_denom`
+
+#[cw_serde]
+struct MockPairInstantiateMsg {
+    ask_denom: String,
+}
+
+const ASK_DENOM: Item<String> = Item::new("ask_denom");
+
+fn mock_pair_instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: MockPairInstantiateMsg,
+) -> StdResult<Response> {
+    ASK_DENOM.save(deps.storage, &msg.ask_denom)?;
+    Ok(Response::new())
+}
+
+fn mock_pair_execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: AstroportPairExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        AstroportPairExecuteMsg::Swap { offer_asset, .. } => {
+            if info.funds.len() != 1 || info.funds[0].amount != offer_asset.amount {
+                return Err(StdError::generic_err("Offer doesn't match funds"));
+            }
+            let ask_denom = ASK_DENOM.load(deps.storage)?;
+            Ok(Response::new().add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: coins(offer_asset.amount.u128() * 2, ask_denom),
+            }))
+        }
+    }
+}
+
+fn mock_pair_query(_deps: Deps, _env: Env, msg: AstroportPairQueryMsg) -> StdResult<Binary> {
+    match msg {
+        AstroportPairQueryMsg::Simulation { offer_asset } => {
+            to_binary(&AstroportSimulationResponse {
+                return_amount: offer_asset.amount * Uint128::from(2_u8),
+                spread_amount: Uint128::zero(),
+                commission_amount: Uint128::zero(),
+            })
+        }
+    }
+}
+
+/// Instantiates a mock pair holding 1_000_000 `ask_denom` to pay out
+pub fn setup_mock_pair(app: &mut BasicApp<OsmosisSwap, Empty>, ask_denom: &str) -> Addr {
+    let code_id = app.store_code(Box::new(ContractWrapper::new_with_empty(
+        mock_pair_execute,
+        mock_pair_instantiate,
+        mock_pair_query,
+    )));
+    let pair_addr = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("owner"),
+            &MockPairInstantiateMsg {
+                ask_denom: ask_denom.into(),
+            },
+            &[],
+            "mock pair",
+            None,
+        )
+        .unwrap();
+    app.init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &pair_addr, coins(1_000_000, ask_denom))
+            .unwrap();
+    });
+    pair_addr
+}
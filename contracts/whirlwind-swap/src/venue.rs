@@ -0,0 +1,376 @@
+use cosmwasm_std::{
+    to_binary, Addr, Coin, Decimal, Decimal256, QuerierWrapper, StdError, StdResult, Uint128,
+    Uint256, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use crate::error::ContractError;
+use crate::msg::{
+    AstroportAsset, AstroportAssetInfo, AstroportCw20HookMsg, AstroportPairExecuteMsg,
+    AstroportPairQueryMsg, AstroportSimulationResponse, OsmosisRoute, OsmosisSwap,
+    OsmosisSwapValue,
+};
+use crate::osmosis::{parse_pool_id, query_estimate_swap_exact_amount_in, query_spot_price};
+use crate::state::{AmountDenom, Denom, PoolVenue};
+
+type CosmosMsg = cosmwasm_std::CosmosMsg<OsmosisSwap>;
+
+pub struct SwapSimulation {
+    pub output_amount: Uint128,
+    // Shortfall of the output relative to swapping at the current spot prices
+    pub price_impact: Decimal256,
+}
+
+/// A DEX the contract can swap locked balances on. Routes are validated against
+/// the allowlist before they reach a venue, and every hop of a route is on the same venue.
+pub trait SwapVenue {
+    /// Messages, sent by `sender`, that swap `offer` along `routes`. Venues that can't
+    /// enforce `min_output` themselves rely on the check in the swap reply.
+    fn swap_msgs(
+        &self,
+        sender: &Addr,
+        routes: &[OsmosisRoute],
+        offer: &AmountDenom,
+        min_output: Uint128,
+        output_denom: &Denom,
+    ) -> Result<Vec<CosmosMsg>, ContractError>;
+
+    fn simulate(
+        &self,
+        querier: &QuerierWrapper,
+        routes: &[OsmosisRoute],
+        offer: &AmountDenom,
+    ) -> StdResult<SwapSimulation>;
+}
+
+impl PoolVenue {
+    pub fn swap_venue(&self) -> Box<dyn SwapVenue> {
+        match self {
+            PoolVenue::Osmosis => Box::new(OsmosisVenue),
+            PoolVenue::Astroport { pair_addr } => Box::new(AstroportVenue {
+                pair_addr: pair_addr.clone(),
+            }),
+        }
+    }
+}
+
+/// How a denom is named in `OsmosisRoute::token_out_denom`
+pub fn route_denom(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.clone(),
+        Denom::Cw20(addr) => addr.to_string(),
+    }
+}
+
+fn price_impact(output_amount: Uint128, spot_output: Uint256) -> Decimal256 {
+    if spot_output.is_zero() || Uint256::from(output_amount) >= spot_output {
+        Decimal256::zero()
+    } else {
+        Decimal256::one() - Decimal256::from_ratio(output_amount, spot_output)
+    }
+}
+
+// MARK: Osmosis
+
+/// Swaps through the Osmosis pool manager
+pub struct OsmosisVenue;
+
+impl SwapVenue for OsmosisVenue {
+    fn swap_msgs(
+        &self,
+        sender: &Addr,
+        routes: &[OsmosisRoute],
+        offer: &AmountDenom,
+        min_output: Uint128,
+        output_denom: &Denom,
+    ) -> Result<Vec<CosmosMsg>, ContractError> {
+        let input_denom = match &offer.denom {
+            Denom::Native(denom) => Ok(denom.clone()),
+            Denom::Cw20(_) => Err(ContractError::Std(StdError::GenericErr {
+                msg: "Not yet supported".into(),
+            })),
+        }?;
+        if let Denom::Cw20(_) = output_denom {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Not yet supported".into(),
+            }));
+        }
+        let msg = CosmosMsg::Custom(OsmosisSwap {
+            _type: "osmosis/gamm/swap-exact-amount-in".into(),
+            value: OsmosisSwapValue {
+                routes: routes.to_vec(),
+                sender: sender.to_string(),
+                token_in: Coin {
+                    denom: input_denom,
+                    amount: offer.amount,
+                },
+                token_out_min_amount: min_output,
+            },
+        });
+        Ok(vec![msg])
+    }
+
+    fn simulate(
+        &self,
+        querier: &QuerierWrapper,
+        routes: &[OsmosisRoute],
+        offer: &AmountDenom,
+    ) -> StdResult<SwapSimulation> {
+        let input_denom = match &offer.denom {
+            Denom::Native(denom) => denom.clone(),
+            Denom::Cw20(_) => return Err(StdError::generic_err("Not yet supported")),
+        };
+        let token_in = Coin {
+            denom: input_denom.clone(),
+            amount: offer.amount,
+        };
+        let output_amount = query_estimate_swap_exact_amount_in(querier, &token_in, routes)?;
+
+        // Output at current spot prices, ignoring the price movement caused by the swap itself
+        let mut spot_output = Uint256::from(offer.amount);
+        let mut denom_in = input_denom;
+        for route in routes.iter() {
+            let spot_price = query_spot_price(
+                querier,
+                parse_pool_id(&route.pool_id)?,
+                &denom_in,
+                &route.token_out_denom,
+            )?;
+            spot_output = spot_output * spot_price;
+            denom_in = route.token_out_denom.clone();
+        }
+
+        Ok(SwapSimulation {
+            output_amount,
+            price_impact: price_impact(output_amount, spot_output),
+        })
+    }
+}
+
+// MARK: Astroport
+
+/// Swaps through an Astroport-style pair contract. Pairs only quote their own two
+/// assets, so routes on this venue are a single hop.
+pub struct AstroportVenue {
+    pub pair_addr: Addr,
+}
+
+// Astroport's largest accepted spread. The requested minimum output is checked in the reply instead
+const ASTROPORT_MAX_SPREAD: u64 = 50;
+
+impl AstroportVenue {
+    fn single_hop(routes: &[OsmosisRoute]) -> StdResult<()> {
+        if routes.len() != 1 {
+            return Err(StdError::generic_err(
+                "Astroport routes must be a single hop",
+            ));
+        }
+        Ok(())
+    }
+
+    fn offer_asset(offer: &AmountDenom) -> AstroportAsset {
+        let info = match &offer.denom {
+            Denom::Native(denom) => AstroportAssetInfo::NativeToken {
+                denom: denom.clone(),
+            },
+            Denom::Cw20(addr) => AstroportAssetInfo::Token {
+                contract_addr: addr.to_string(),
+            },
+        };
+        AstroportAsset {
+            info,
+            amount: offer.amount,
+        }
+    }
+}
+
+impl SwapVenue for AstroportVenue {
+    fn swap_msgs(
+        &self,
+        _sender: &Addr,
+        routes: &[OsmosisRoute],
+        offer: &AmountDenom,
+        _min_output: Uint128,
+        _output_denom: &Denom,
+    ) -> Result<Vec<CosmosMsg>, ContractError> {
+        AstroportVenue::single_hop(routes)?;
+        let max_spread = Some(Decimal::percent(ASTROPORT_MAX_SPREAD));
+
+        let msg = match &offer.denom {
+            Denom::Native(denom) => WasmMsg::Execute {
+                contract_addr: self.pair_addr.to_string(),
+                msg: to_binary(&AstroportPairExecuteMsg::Swap {
+                    offer_asset: AstroportVenue::offer_asset(offer),
+                    belief_price: None,
+                    max_spread,
+                    to: None,
+                })?,
+                funds: vec![Coin {
+                    denom: denom.clone(),
+                    amount: offer.amount,
+                }],
+            },
+            // CW20 offers are sent to the pair with the swap as the hook message
+            Denom::Cw20(addr) => WasmMsg::Execute {
+                contract_addr: addr.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: self.pair_addr.to_string(),
+                    amount: offer.amount,
+                    msg: to_binary(&AstroportCw20HookMsg::Swap {
+                        belief_price: None,
+                        max_spread,
+                        to: None,
+                    })?,
+                })?,
+                funds: vec![],
+            },
+        };
+        Ok(vec![CosmosMsg::Wasm(msg)])
+    }
+
+    fn simulate(
+        &self,
+        querier: &QuerierWrapper,
+        routes: &[OsmosisRoute],
+        offer: &AmountDenom,
+    ) -> StdResult<SwapSimulation> {
+        AstroportVenue::single_hop(routes)?;
+        let AstroportSimulationResponse {
+            return_amount,
+            spread_amount,
+            commission_amount,
+        } = querier.query_wasm_smart(
+            self.pair_addr.clone(),
+            &AstroportPairQueryMsg::Simulation {
+                offer_asset: AstroportVenue::offer_asset(offer),
+            },
+        )?;
+
+        // The pair reports what the spot price would have paid out as return + spread + commission
+        let spot_output = Uint256::from(return_amount)
+            + Uint256::from(spread_amount)
+            + Uint256::from(commission_amount);
+        Ok(SwapSimulation {
+            output_amount: return_amount,
+            price_impact: price_impact(return_amount, spot_output),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::setup_mock_pair;
+    use cosmwasm_std::{coins, Empty};
+    use cw_multi_test::{BasicAppBuilder, Executor};
+
+    #[test]
+    fn test_astroport_venue_swap() {
+        let swapper = Addr::unchecked("swapper");
+        let mut app =
+            BasicAppBuilder::<OsmosisSwap, Empty>::new_custom().build(|router, _, storage| {
+                router
+                    .bank
+                    .init_balance(storage, &swapper, coins(1_000, "uosmo"))
+                    .unwrap();
+            });
+        let pair_addr = setup_mock_pair(&mut app, "uusdc");
+        let venue = PoolVenue::Astroport { pair_addr }.swap_venue();
+
+        let routes = vec![OsmosisRoute {
+            pool_id: "astro-osmo-usdc".into(),
+            token_out_denom: "uusdc".into(),
+        }];
+        let offer = AmountDenom {
+            amount: Uint128::from(100_u128),
+            denom: Denom::Native("uosmo".into()),
+        };
+
+        let simulation = venue.simulate(&app.wrap(), &routes, &offer).unwrap();
+        assert_eq!(simulation.output_amount, Uint128::from(200_u128));
+        assert_eq!(simulation.price_impact, Decimal256::zero());
+
+        let msgs = venue
+            .swap_msgs(
+                &swapper,
+                &routes,
+                &offer,
+                Uint128::from(200_u128),
+                &Denom::Native("uusdc".into()),
+            )
+            .unwrap();
+        for msg in msgs {
+            app.execute(swapper.clone(), msg).unwrap();
+        }
+
+        let balance = app.wrap().query_balance(&swapper, "uusdc").unwrap();
+        assert_eq!(balance.amount, Uint128::from(200_u128));
+        let balance = app.wrap().query_balance(&swapper, "uosmo").unwrap();
+        assert_eq!(balance.amount, Uint128::from(900_u128));
+    }
+
+    #[test]
+    fn test_astroport_venue_single_hop() {
+        let venue = AstroportVenue {
+            pair_addr: Addr::unchecked("pair"),
+        };
+        let routes = vec![
+            OsmosisRoute {
+                pool_id: "a".into(),
+                token_out_denom: "uatom".into(),
+            },
+            OsmosisRoute {
+                pool_id: "b".into(),
+                token_out_denom: "uusdc".into(),
+            },
+        ];
+        let offer = AmountDenom {
+            amount: Uint128::from(100_u128),
+            denom: Denom::Native("uosmo".into()),
+        };
+        let res = venue.swap_msgs(
+            &Addr::unchecked("swapper"),
+            &routes,
+            &offer,
+            Uint128::zero(),
+            &Denom::Native("uusdc".into()),
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_osmosis_venue_swap_msg() {
+        let routes = vec![OsmosisRoute {
+            pool_id: "1".into(),
+            token_out_denom: "uusdc".into(),
+        }];
+        let offer = AmountDenom {
+            amount: Uint128::from(100_u128),
+            denom: Denom::Native("uosmo".into()),
+        };
+        let msgs = OsmosisVenue
+            .swap_msgs(
+                &Addr::unchecked("contract"),
+                &routes,
+                &offer,
+                Uint128::from(150_u128),
+                &Denom::Native("uusdc".into()),
+            )
+            .unwrap();
+        assert_eq!(
+            msgs,
+            vec![CosmosMsg::Custom(OsmosisSwap {
+                _type: "osmosis/gamm/swap-exact-amount-in".into(),
+                value: OsmosisSwapValue {
+                    routes,
+                    sender: "contract".into(),
+                    token_in: Coin {
+                        denom: "uosmo".into(),
+                        amount: Uint128::from(100_u128),
+                    },
+                    token_out_min_amount: Uint128::from(150_u128),
+                },
+            })]
+        );
+    }
+}
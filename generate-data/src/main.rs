@@ -8,11 +8,12 @@ use ark_circom::{CircomConfig, CircomBuilder};
 use ark_groth16::Groth16;
 use ark_std::rand::thread_rng;
 use ark_crypto_primitives::snark::SNARK;
-use lib::{poseidon::{Poseidon}, merkle_tree::MerkleTreeWithHistory};
+use lib::{poseidon::{Poseidon}, merkle_tree::{MerkleTreeWithHistory, verify_path}};
+use lib::sparse_merkle_tree::{SmtCheckerInputs, SmtProof, SparseMerkleTreeStore};
 
 type GrothBn = Groth16<Bn254>;
 
-use cosmwasm_std::Uint256;
+use cosmwasm_std::{MemoryStorage, Uint256};
 
 fn U256(value: &str) -> Uint256 {
     Uint256::from_str(value).unwrap()
@@ -27,6 +28,7 @@ struct Deposit {
     // Public
     walletAddress: String,
     credential: String,
+    tierId: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -41,6 +43,18 @@ struct Migrate {
     depositTreeRoot: String,
     nullifier: String,
     previousNullifier: String,
+    tierId: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Reclaim {
+    // Private
+    secret: String,
+    // Public
+    walletAddress: String,
+    credential: String,
+    nullifier: String,
+    tierId: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -64,6 +78,47 @@ struct Swap {
     newNftCredential: String,
 }
 
+// Cases for `circuits/lib/test/test_sparse_merkle_tree.sh`, which checks them against
+// `circuits/lib/test/sparseMerkleTree.circom`. Names starting with `invalid_` must not
+// satisfy the circuit
+fn write_sparse_merkle_tree_checks() {
+    const TREE: SparseMerkleTreeStore = SparseMerkleTreeStore::new("levels", "nodes", "leaves");
+    const LEVELS: u32 = 8;
+    let mut storage = MemoryStorage::new();
+    TREE.init(&mut storage, 0, LEVELS).unwrap();
+
+    let key = U256("1337");
+    let value = U256("42");
+    // Shares its lowest 4 bits with `key`, so both leaves sit 5 deep
+    let pushed_down_key = U256("1353");
+    TREE.insert(&mut storage, 0, &key, &value).unwrap();
+    TREE.insert(&mut storage, 0, &pushed_down_key, &U256("1")).unwrap();
+    TREE.insert(&mut storage, 0, &U256("6"), &U256("1")).unwrap();
+
+    // Paths ending at an empty node and at the leaf of 6
+    let absent_key = U256("3");
+    let occupied_key = U256("2");
+
+    let check = |proof: &SmtProof, key: Uint256, value: Uint256, fnc: u32| {
+        SmtCheckerInputs::new(proof, key, value, fnc, LEVELS).unwrap()
+    };
+    let key_proof = TREE.prove(&storage, 0, &key).unwrap();
+    let checks = vec![
+        ("inclusion", check(&key_proof, key, value, 0)),
+        ("inclusion_pushed_down", check(&TREE.prove(&storage, 0, &pushed_down_key).unwrap(), pushed_down_key, U256("1"), 0)),
+        ("exclusion_empty", check(&TREE.prove(&storage, 0, &absent_key).unwrap(), absent_key, Uint256::zero(), 1)),
+        ("exclusion_occupied", check(&TREE.prove(&storage, 0, &occupied_key).unwrap(), occupied_key, Uint256::zero(), 1)),
+        ("invalid_inclusion_wrong_value", check(&key_proof, key, U256("43"), 0)),
+        ("invalid_exclusion_of_member", check(&key_proof, key, Uint256::zero(), 1)),
+    ];
+
+    std::fs::create_dir_all("outputs/smt").unwrap();
+    for (name, check) in checks {
+        let mut file = File::create(format!("outputs/smt/{}.json", name)).unwrap();
+        file.write_all(serde_json::to_string_pretty(&check).unwrap().as_bytes()).unwrap();
+    }
+}
+
 // Define a generic function to insert output data of any type into a HashMap
 fn insert_output_data<T: 'static + Serialize >(output_map: &mut HashMap<String, serde_json::Value>, key: String, data: T) {
     let boxed_data: Box<T> = Box::new(data);
@@ -79,13 +134,7 @@ fn insert_output_data<T: 'static + Serialize >(output_map: &mut HashMap<String,
 pub fn poseidon_hash(inputs: Vec<Uint256>) -> Uint256 {
     let poseidon = Poseidon::new();
 
-    // Convert each Uint256 to a [u8; 32]
-    let mut input_vecs: Vec<[u8; 32]> = Vec::new();
-    for inp in inputs {
-        input_vecs.push(inp.to_le_bytes());
-    }
-
-    let res = poseidon.hash_as_u256(input_vecs).unwrap();
+    let res = poseidon.hash_u256(&inputs).unwrap();
     return res;
 }
 
@@ -97,7 +146,8 @@ fn main() {
     let wallet_address = U256("1337");
     let secret = U256("8000");
     let previousSecret = U256("8001");
-    let credential = poseidon_hash(vec![wallet_address, secret]);
+    let tier_id = U256("0");
+    let credential = poseidon_hash(vec![tier_id, poseidon_hash(vec![wallet_address, secret])]);
     let nullifier = poseidon_hash(vec![wallet_address, secret, U256("1")]);
     let previousCredential = poseidon_hash(vec![wallet_address, previousSecret]);
     let previousNullifier = poseidon_hash(vec![wallet_address, previousSecret, U256("1")]);
@@ -110,11 +160,16 @@ fn main() {
     insert_output_data(&mut output_map, "deposit1".to_string(), Deposit {
         walletAddress: wallet_address.to_string(),
         secret: secret.to_string(),
-        credential: credential.to_string()
+        credential: credential.to_string(),
+        tierId: tier_id.to_string(),
     });
 
     // Migrate
-
+    // Catch a bad path here rather than when witness generation fails
+    assert!(
+        verify_path(&credential, &path_elements, &path_indices, &root),
+        "migrate path doesn't lead to the deposit tree root"
+    );
 
     insert_output_data(&mut output_map, "migrate1".to_string(), Migrate {
         walletAddress: wallet_address.to_string(),
@@ -125,6 +180,16 @@ fn main() {
         depositTreeRoot: root.to_string(),
         nullifier: nullifier.to_string(),
         previousNullifier: previousNullifier.to_string(),
+        tierId: tier_id.to_string(),
+    });
+
+    // Reclaim
+    insert_output_data(&mut output_map, "reclaim1".to_string(), Reclaim {
+        secret: secret.to_string(),
+        walletAddress: wallet_address.to_string(),
+        credential: credential.to_string(),
+        nullifier: nullifier.to_string(),
+        tierId: tier_id.to_string(),
     });
 
     // Swap
@@ -159,5 +224,7 @@ fn main() {
     // Write the JSON string to the file
     file.write_all(output_json.as_bytes()).unwrap();
 
+    write_sparse_merkle_tree_checks();
+
 
 }
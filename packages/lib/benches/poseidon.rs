@@ -0,0 +1,61 @@
+use ark_ff::{BigInteger, PrimeField};
+use arkworks_native_gadgets::poseidon::{
+    FieldHasher, Poseidon as ArkworksPoseidon, PoseidonParameters,
+};
+use arkworks_native_gadgets::to_field_elements;
+use arkworks_setups::common::setup_params;
+use arkworks_setups::Curve;
+use cosmwasm_std::Uint256 as U256;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use lib::merkle_tree::MerkleTreeWithHistory;
+use lib::poseidon::Poseidon;
+
+// How `Poseidon::new()` and `hash` used to work: derive the parameters of all three
+// widths, then parse the ones for the input width on every hash
+fn hash_with_setup(inputs: &[U256]) -> U256 {
+    let params: Vec<Vec<u8>> = (3..=5)
+        .map(|width| setup_params::<ark_bn254::Fr>(Curve::Bn254, 5, width).to_bytes())
+        .collect();
+    let poseidon = ArkworksPoseidon::new(
+        PoseidonParameters::<ark_bn254::Fr>::from_bytes(&params[inputs.len() - 2]).unwrap(),
+    );
+
+    let packed: Vec<u8> = inputs
+        .iter()
+        .flat_map(|input| input.to_le_bytes())
+        .collect();
+    let input_f = to_field_elements::<ark_bn254::Fr>(&packed).unwrap();
+    let output: ark_bn254::Fr = poseidon.hash(&input_f).unwrap();
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&output.into_repr().to_bytes_le());
+    U256::from_le_bytes(bytes)
+}
+
+fn bench_poseidon(c: &mut Criterion) {
+    let inputs = [U256::from(1_u32), U256::from(2_u32)];
+    assert_eq!(
+        hash_with_setup(&inputs),
+        Poseidon::new().hash_u256(&inputs).unwrap()
+    );
+
+    let mut group = c.benchmark_group("poseidon_hash_2");
+    group.bench_function("setup_per_hash", |b| b.iter(|| hash_with_setup(&inputs)));
+    group.bench_function("cached", |b| {
+        b.iter(|| Poseidon::new().hash_u256(&inputs).unwrap())
+    });
+    group.finish();
+
+    // 20 levels, so 20 hashes per insert
+    c.bench_function("merkle_tree_insert_20_levels", |b| {
+        let mut mt = MerkleTreeWithHistory::new(20);
+        let mut leaf = U256::zero();
+        b.iter(|| {
+            leaf += U256::one();
+            mt.insert(&leaf)
+        })
+    });
+}
+
+criterion_group!(benches, bench_poseidon);
+criterion_main!(benches);
@@ -1,6 +1,10 @@
 pub mod merkle_tree;
 pub mod msg;
+pub mod sparse_merkle_tree;
+pub mod storage_tree;
+pub mod tree_hasher;
 pub mod verifier;
+pub mod zeros;
 
 pub mod poseidon;
 
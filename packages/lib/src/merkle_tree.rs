@@ -1,15 +1,18 @@
 #![allow(non_snake_case)]
 
 use crate::bignum;
-use crate::poseidon::Poseidon;
+use crate::poseidon::{poseidon, Poseidon};
+use crate::tree_hasher::{PoseidonHasher, TreeHasher};
+use crate::zeros::ZEROS;
 use cosmwasm_std::Uint256 as U256;
+use std::marker::PhantomData;
 
 use serde::{Deserialize, Serialize};
 
-const ROOT_HISTORY_SIZE: u32 = 100;
+pub const DEFAULT_ROOT_HISTORY_SIZE: u32 = 100;
 
 #[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub struct MerkleTreeWithHistory {
+pub struct MerkleTreeWithHistory<H: TreeHasher = PoseidonHasher> {
     pub levels: u32,
     pub filled_subtrees: Vec<U256>,
     pub zeros: Vec<U256>,
@@ -18,181 +21,375 @@ pub struct MerkleTreeWithHistory {
     pub roots: Vec<U256>,
 
     pub ZERO_VALUE: U256,
+
+    // Not stored, so serialized trees are the same for every hasher
+    #[serde(skip)]
+    hasher: PhantomData<H>,
 }
 
 impl MerkleTreeWithHistory {
     pub fn new(levels: u32) -> Self {
-        let mut this: Self = Default::default();
+        Self::with_root_history_size(levels, DEFAULT_ROOT_HISTORY_SIZE)
+    }
+
+    /// Tree that keeps its last `root_history_size` roots as known roots
+    pub fn with_root_history_size(levels: u32, root_history_size: u32) -> Self {
+        Self::with_hasher(levels, root_history_size)
+    }
+}
+
+impl<H: TreeHasher> MerkleTreeWithHistory<H> {
+    /// Tree hashing with `H` instead of Poseidon
+    pub fn with_hasher(levels: u32, root_history_size: u32) -> Self {
+        let mut this = Self {
+            levels: 0,
+            filled_subtrees: vec![],
+            zeros: vec![],
+            current_root_index: 0,
+            next_index: 0,
+            roots: vec![],
+            ZERO_VALUE: U256::zero(),
+            hasher: PhantomData,
+        };
         assert!(levels > 0, "_treeLevels should be greater than zero");
         assert!(levels < 32, "_treeLevels should be less than 32");
+        assert!(root_history_size > 0, "_rootHistorySize should be greater than zero");
 
         let ZERO_VALUE = bignum!(
             "21663839004416932945382355908790599225266501822907911457504978515578255421292"
         );
 
         this.levels = levels;
-        this.roots = vec![U256::zero(); ROOT_HISTORY_SIZE as usize];
+        this.roots = vec![U256::zero(); root_history_size as usize];
 
         this.ZERO_VALUE = ZERO_VALUE;
 
-        let mut current_zero = ZERO_VALUE;
-        this.zeros.push(current_zero);
-        this.filled_subtrees.push(current_zero);
-
-        for _ in 1..levels {
-            current_zero = this.hash_left_right(&current_zero, &current_zero);
-            this.zeros.push(current_zero);
-            this.filled_subtrees.push(current_zero);
-        }
-
-        this.roots[0] = this.hash_left_right(&current_zero, &current_zero);
+        this.zeros = H::zeros(levels as usize);
+        this.filled_subtrees = this.zeros[..levels as usize].to_vec();
+        this.roots[0] = this.zeros.pop().unwrap();
         this
     }
 
-    pub fn hash_left_right(&self, left: &U256, right: &U256) -> U256 {
-        let poseidon = Poseidon::new();
-        // let mut left_bytes: [u8; 32] = [0; 32];
-        // let mut right_bytes: [u8; 32] = [0; 32];
-
-        let left_bytes = left.to_le_bytes();
-        let right_bytes = right.to_le_bytes();
-
-        let inputs = vec![left_bytes, right_bytes];
-
-        poseidon.hash_as_u256(inputs).unwrap()
+    pub fn hash_left_right(&self, left: &U256, right: &U256) -> Option<U256> {
+        H::hash_left_right(left, right)
     }
 
+    /// Inserts `leaf` and returns its index. Returns `None` if the tree is full or the
+    /// leaf can't be hashed, leaving the tree as it was.
     pub fn insert(&mut self, leaf: &U256) -> Option<u32> {
+        self.insert_and_return_path(leaf).map(|(index, _, _)| index)
+    }
+
+    pub fn insert_and_return_path(&mut self, leaf: &U256) -> Option<(u32, Vec<U256>, Vec<U256>)> {
         let mut idx = self.next_index;
         if idx == 2_u32.saturating_pow(self.levels) {
             //"Merkle tree is full. No more leafs can be added");
             return None;
         }
 
-        self.next_index += 1;
+        // Nothing is updated until every hash succeeded
+        let mut filled_subtrees = self.filled_subtrees.clone();
         let mut current_level_hash: U256 = *leaf;
-        let mut left: &U256;
-        let mut right: &U256;
-
-        for i in 0..(self.levels) {
-            if idx % 2 == 0 {
-                // println!("level: {}. left", idx);
-
-                left = &current_level_hash;
-                right = &self.zeros[i as usize];
+        let mut path_indices = Vec::new();
+        let mut path_elements = Vec::new();
 
-                // println!("current_level_hash: {}", right);
+        for (subtree, zero) in filled_subtrees.iter_mut().zip(self.zeros.iter()) {
+            let (left, right) = if idx % 2 == 0 {
+                *subtree = current_level_hash;
+                path_indices.push(U256::zero());
+                path_elements.push(*zero);
+                (current_level_hash, *zero)
+            } else {
+                path_indices.push(U256::one());
+                path_elements.push(*subtree);
+                (*subtree, current_level_hash)
+            };
 
+            current_level_hash = self.hash_left_right(&left, &right)?;
+            idx /= 2;
+        }
 
-                self.filled_subtrees[i as usize] = current_level_hash;
-            } else {
-                // println!("level: {}. right", idx);
+        self.filled_subtrees = filled_subtrees;
+        self.next_index += 1;
+        self.current_root_index = (self.current_root_index + 1) % self.root_history_size();
+        self.roots[self.current_root_index as usize] = current_level_hash;
 
-                left = &self.filled_subtrees[i as usize];
-                right = &current_level_hash;
+        Some((self.next_index - 1, path_indices, path_elements))
+    }
 
-                // println!("current_level_hash: {}", left);
+    /// Inserts `leaves` in order and returns the index of the first one. Parents shared
+    /// by several new leaves are hashed once, and only the final root is recorded.
+    pub fn insert_batch(&mut self, leaves: &[U256]) -> Option<u32> {
+        let start = self.next_index;
+        if leaves.is_empty() || (start as u64 + leaves.len() as u64) > 2_u64.pow(self.levels) {
+            return None;
+        }
 
-            }
+        let mut filled_subtrees = self.filled_subtrees.clone();
+        let root = batch_root::<H>(&self.zeros, &mut filled_subtrees, start, leaves)?;
 
-            current_level_hash = self.hash_left_right(left, right);
+        self.filled_subtrees = filled_subtrees;
+        self.next_index += leaves.len() as u32;
+        self.current_root_index = (self.current_root_index + 1) % self.root_history_size();
+        self.roots[self.current_root_index as usize] = root;
 
+        Some(start)
+    }
 
-            idx /= 2;
+    pub fn is_known_root(&self, root: &U256) -> bool {
+        if root.is_zero() {
+            return false;
         }
-        // println!("root: {}", current_level_hash);
+        let root_history_size = self.root_history_size();
+        let mut i = self.current_root_index;
 
-        // println!("\n\n\n\n");
+        for _ in 0..root_history_size {
+            if *root == self.roots[i as usize] {
+                return true;
+            }
+            if i == 0 {
+                i = root_history_size;
+            }
 
+            i -= 1;
 
+            if i == self.current_root_index {
+                break;
+            }
+        }
 
+        false
+    }
 
-        self.current_root_index = (self.current_root_index + 1) % ROOT_HISTORY_SIZE;
-        self.roots[self.current_root_index as usize] = current_level_hash;
+    pub fn get_last_root(&self) -> U256 {
+        self.roots[self.current_root_index as usize]
+    }
 
-        Some(self.next_index - 1)
+    pub fn root_history_size(&self) -> u32 {
+        self.roots.len() as u32
     }
+}
 
-    pub fn insert_and_return_path(&mut self, leaf: &U256) -> Option<(u32, Vec<U256>, Vec<U256>)> {
-        let mut idx = self.next_index;
-        if idx == 2_u32.saturating_pow(self.levels) {
-            //"Merkle tree is full. No more leafs can be added");
-            return None;
+/// Hashes `leaves`, placed from index `start`, up to the root, one level at a time.
+/// `filled_subtrees` holds the left siblings the new nodes need and is updated like
+/// inserting the leaves one by one would. Returns `None` if a node can't be hashed.
+pub(crate) fn batch_root<H: TreeHasher>(
+    zeros: &[U256],
+    filled_subtrees: &mut [U256],
+    start: u32,
+    leaves: &[U256],
+) -> Option<U256> {
+    let mut start = start as usize;
+    let mut nodes = leaves.to_vec();
+
+    for i in 0..filled_subtrees.len() {
+        let end = start + nodes.len();
+        let mut parents = Vec::with_capacity(nodes.len() / 2 + 1);
+        for parent in (start / 2)..=((end - 1) / 2) {
+            let left = if 2 * parent < start {
+                filled_subtrees[i]
+            } else {
+                nodes[2 * parent - start]
+            };
+            let right = if 2 * parent + 1 < end {
+                nodes[2 * parent + 1 - start]
+            } else {
+                zeros[i]
+            };
+            parents.push(H::hash_left_right(&left, &right)?);
         }
 
-        self.next_index += 1;
-        let mut current_level_hash: U256 = *leaf;
-        let mut left: &U256;
-        let mut right: &U256;
-
-        let mut path_indices = Vec::new();
-        let mut path_elements = Vec::new();
+        // The last new left child is what later inserts pair with
+        let last_left = if (end - 1) % 2 == 0 { end - 1 } else { end - 2 };
+        if last_left >= start {
+            filled_subtrees[i] = nodes[last_left - start];
+        }
 
-        for i in 0..(self.levels) {
-            if idx % 2 == 0 {
+        nodes = parents;
+        start /= 2;
+    }
 
-                left = &current_level_hash;
-                right = &self.zeros[i as usize];
+    Some(nodes[0])
+}
 
+/// Native counterpart of `MerkleTreeChecker` in `circuits/lib/merkleTree.circom`.
+/// Each path index selects, like `DualMux`, whether the current hash is the left (0)
+/// or right (1) input of the next hash. Indices other than 0 or 1 fail the circuit's
+/// constraint, so they fail here too.
+pub fn verify_path(leaf: &U256, path_elements: &[U256], path_indices: &[U256], root: &U256) -> bool {
+    if path_elements.is_empty() || path_elements.len() != path_indices.len() {
+        return false;
+    }
 
-                path_indices.push(U256::zero());
-                path_elements.push(right.clone());
+    let poseidon = Poseidon::new();
+    let mut current = *leaf;
+    for (element, index) in path_elements.iter().zip(path_indices.iter()) {
+        let (left, right) = if index.is_zero() {
+            (current, *element)
+        } else if *index == U256::one() {
+            (*element, current)
+        } else {
+            return false;
+        };
+        current = match poseidon.hash_u256(&[left, right]) {
+            Ok(hash) => hash,
+            Err(_) => return false,
+        };
+    }
 
+    current == *root
+}
 
-                self.filled_subtrees[i as usize] = current_level_hash;
-            } else {
+/// Merkle tree that keeps every leaf, so paths can be built for any leaf against any
+/// root the tree has had. Hashing and zero values are the ones of
+/// `MerkleTreeWithHistory`, so roots match the contract's.
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FullMerkleTree {
+    pub levels: u32,
+    pub leaves: Vec<U256>,
+}
 
-                left = &self.filled_subtrees[i as usize];
-                right = &current_level_hash;
+impl FullMerkleTree {
+    pub fn new(levels: u32) -> Self {
+        assert!(levels > 0, "_treeLevels should be greater than zero");
+        assert!(levels < 32, "_treeLevels should be less than 32");
 
-                path_indices.push(U256::one());
-                path_elements.push(left.clone());
-            }
+        Self {
+            levels,
+            leaves: vec![],
+        }
+    }
 
-            current_level_hash = self.hash_left_right(left, right);
+    /// Tree holding `leaves` in order. Returns `None` if they don't fit.
+    pub fn from_leaves(levels: u32, leaves: Vec<U256>) -> Option<Self> {
+        let mut this = Self::new(levels);
+        for leaf in leaves.iter() {
+            this.insert(leaf)?;
+        }
+        Some(this)
+    }
 
+    /// Rebuilds a tree from `(leaf_index, leaf)` pairs, such as the `commitment` events
+    /// emitted on deposit. Pairs may come in any order, but every index from zero up
+    /// to the last one must appear exactly once.
+    pub fn from_indexed_leaves(
+        levels: u32,
+        indexed_leaves: impl IntoIterator<Item = (u32, U256)>,
+    ) -> Option<Self> {
+        let mut indexed_leaves: Vec<(u32, U256)> = indexed_leaves.into_iter().collect();
+        indexed_leaves.sort_by_key(|(index, _)| *index);
+
+        let mut leaves = vec![];
+        for (expected, (index, leaf)) in indexed_leaves.into_iter().enumerate() {
+            if index as usize != expected {
+                return None;
+            }
+            leaves.push(leaf);
+        }
+        Self::from_leaves(levels, leaves)
+    }
 
-            idx /= 2;
+    /// Appends `leaf` and returns its index. Returns `None` if the tree is full or the
+    /// leaf isn't a field element, as it couldn't be hashed.
+    pub fn insert(&mut self, leaf: &U256) -> Option<u32> {
+        let index = self.leaves.len() as u32;
+        if index == 2_u32.saturating_pow(self.levels) {
+            //"Merkle tree is full. No more leafs can be added");
+            return None;
         }
+        poseidon::u256_to_fr(leaf).ok()?;
 
-        self.current_root_index = (self.current_root_index + 1) % ROOT_HISTORY_SIZE;
-        self.roots[self.current_root_index as usize] = current_level_hash;
+        self.leaves.push(*leaf);
+        Some(index)
+    }
 
-        Some((self.next_index - 1, path_indices, path_elements))
+    pub fn get_last_root(&self) -> U256 {
+        self.root_at(self.leaves.len() as u32).unwrap()
     }
 
-    pub fn is_known_root(&self, root: &U256) -> bool {
-        if root == &U256::zero() {
-            return false;
-        }
-        let mut i = self.current_root_index;
+    /// Root of the tree when it held its first `leaf_count` leaves
+    pub fn root_at(&self, leaf_count: u32) -> Option<U256> {
+        let layers = self.layers(leaf_count)?;
+        Some(
+            layers[self.levels as usize]
+                .first()
+                .copied()
+                .unwrap_or(ZEROS[self.levels as usize]),
+        )
+    }
 
-        for _ in 0..ROOT_HISTORY_SIZE {
-            if *root == self.roots[i as usize] {
-                return true;
-            }
-            if i == 0 {
-                i = ROOT_HISTORY_SIZE;
+    /// Number of leaves the tree held when `root` was its root
+    pub fn leaf_count_of_root(&self, root: &U256) -> Option<u32> {
+        // Replays the inserts, which costs one hash per level and leaf
+        let mut mt = MerkleTreeWithHistory::with_root_history_size(self.levels, 1);
+        if mt.get_last_root() == *root {
+            return Some(0);
+        }
+        for leaf in self.leaves.iter() {
+            mt.insert(leaf);
+            if mt.get_last_root() == *root {
+                return Some(mt.next_index);
             }
+        }
+        None
+    }
 
-            i -= 1;
+    /// `(path_indices, path_elements)` of leaf `index` against `root`, in the
+    /// format `insert_and_return_path` returns
+    pub fn get_path(&self, index: u32, root: &U256) -> Option<(Vec<U256>, Vec<U256>)> {
+        let leaf_count = self.leaf_count_of_root(root)?;
+        self.get_path_at(index, leaf_count)
+    }
 
-            if i == self.current_root_index {
-                break;
-            }
+    /// `(path_indices, path_elements)` of leaf `index` against the root the tree had
+    /// with its first `leaf_count` leaves
+    pub fn get_path_at(&self, index: u32, leaf_count: u32) -> Option<(Vec<U256>, Vec<U256>)> {
+        if index >= leaf_count {
+            return None;
         }
+        let layers = self.layers(leaf_count)?;
 
-        false
+        let mut path_indices = Vec::new();
+        let mut path_elements = Vec::new();
+        let mut idx = index as usize;
+        for i in 0..(self.levels as usize) {
+            path_indices.push(U256::from((idx % 2) as u32));
+            path_elements.push(layers[i].get(idx ^ 1).copied().unwrap_or(ZEROS[i]));
+            idx /= 2;
+        }
+
+        Some((path_indices, path_elements))
     }
 
-    pub fn get_last_root(&self) -> U256 {
-        self.roots[self.current_root_index as usize]
+    /// Nodes of every level, from the leaves up to the root, leaving out subtrees that
+    /// only hold zeros. Returns `None` if there aren't `leaf_count` leaves or a node
+    /// can't be hashed.
+    fn layers(&self, leaf_count: u32) -> Option<Vec<Vec<U256>>> {
+        if leaf_count as usize > self.leaves.len() {
+            return None;
+        }
+
+        let poseidon = Poseidon::new();
+        let mut layers = vec![self.leaves[..leaf_count as usize].to_vec()];
+        for i in 0..(self.levels as usize) {
+            let layer = layers[i]
+                .chunks(2)
+                .map(|pair| {
+                    let right = pair.get(1).unwrap_or(&ZEROS[i]);
+                    poseidon.hash_u256(&[pair[0], *right]).ok()
+                })
+                .collect::<Option<_>>()?;
+            layers.push(layer);
+        }
+
+        Some(layers)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tree_hasher::SumHasher;
     use cosmwasm_std::Uint256 as U256;
 
     #[test]
@@ -216,6 +413,21 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_merkletree_root_history_size() {
+        let mut mt = MerkleTreeWithHistory::with_root_history_size(20, 2);
+        let empty_root = mt.get_last_root();
+        mt.insert(&U256::from(1_u32));
+        let first_root = mt.get_last_root();
+        mt.insert(&U256::from(2_u32));
+        assert!(mt.is_known_root(&first_root));
+        assert!(!mt.is_known_root(&empty_root));
+
+        mt.insert(&U256::from(3_u32));
+        assert!(!mt.is_known_root(&first_root));
+        assert!(mt.is_known_root(&mt.get_last_root()));
+    }
+
     #[test]
     fn test_merkletree_insert_single_01() {
         let mut mt = MerkleTreeWithHistory::new(20);
@@ -227,4 +439,172 @@ mod tests {
         assert_eq!(root, expected);
     }
 
+    #[test]
+    fn test_merkletree_insert_batch() {
+        let leaves: Vec<U256> = (1..=11_u32).map(U256::from).collect();
+
+        // Batches of every size, starting from odd and even indices
+        for split in [0_usize, 1, 2, 5] {
+            let mut sequential = MerkleTreeWithHistory::new(20);
+            let mut batched = MerkleTreeWithHistory::new(20);
+            for leaf in leaves[..split].iter() {
+                sequential.insert(leaf);
+                batched.insert(leaf);
+            }
+            for batch_size in 1..=(leaves.len() - split) {
+                let mut sequential = sequential.clone();
+                let mut batched = batched.clone();
+                for leaf in leaves[split..split + batch_size].iter() {
+                    sequential.insert(leaf);
+                }
+                let first = batched.insert_batch(&leaves[split..split + batch_size]);
+                assert_eq!(first, Some(split as u32));
+                assert_eq!(batched.get_last_root(), sequential.get_last_root());
+                assert_eq!(batched.filled_subtrees, sequential.filled_subtrees);
+                assert_eq!(batched.next_index, sequential.next_index);
+
+                // Later inserts continue the same tree
+                sequential.insert(&U256::from(42_u32));
+                batched.insert(&U256::from(42_u32));
+                assert_eq!(batched.get_last_root(), sequential.get_last_root());
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkletree_insert_batch_root_history() {
+        let mut mt = MerkleTreeWithHistory::new(2);
+        let empty_root = mt.get_last_root();
+        assert_eq!(mt.insert_batch(&[]), None);
+        assert_eq!(mt.insert_batch(&[U256::one(); 5]), None);
+
+        // Only the final root is recorded
+        assert_eq!(mt.insert_batch(&[U256::one(); 4]), Some(0));
+        assert_eq!(mt.current_root_index, 1);
+        assert!(mt.is_known_root(&empty_root));
+        assert_eq!(mt.insert(&U256::one()), None);
+    }
+
+    #[test]
+    fn test_merkletree_custom_hasher() {
+        let mut mt = MerkleTreeWithHistory::<SumHasher>::with_hasher(4, 10);
+        assert_eq!(mt.zeros[1], mt.ZERO_VALUE.wrapping_mul(U256::from(4_u32)));
+        assert_eq!(mt.zeros, SumHasher::zeros(3));
+
+        let mut batched = mt.clone();
+        let leaves: Vec<U256> = (1..=16_u32).map(U256::from).collect();
+        for leaf in leaves.iter() {
+            mt.insert(leaf);
+        }
+        assert_eq!(mt.insert(&U256::one()), None);
+        assert_eq!(batched.insert_batch(&leaves), Some(0));
+        assert_eq!(batched.get_last_root(), mt.get_last_root());
+
+        // The hasher isn't part of the stored tree
+        let stored = serde_json::to_string(&mt).unwrap();
+        let poseidon_tree: MerkleTreeWithHistory = serde_json::from_str(&stored).unwrap();
+        assert_eq!(poseidon_tree.get_last_root(), mt.get_last_root());
+    }
+
+    #[test]
+    fn test_merkletree_rejects_out_of_field_leaf() {
+        let mut mt = MerkleTreeWithHistory::new(20);
+        mt.insert(&U256::from(42_u32));
+        let before = mt.clone();
+
+        // Poseidon won't hash values past the field modulus, and the tree is left as is
+        assert_eq!(mt.insert(&U256::MAX), None);
+        assert_eq!(mt.insert_batch(&[U256::one(), U256::MAX]), None);
+        assert_eq!(mt, before);
+        assert_eq!(mt.insert(&U256::from(7_u32)), Some(1));
+
+        let mut full = FullMerkleTree::new(20);
+        assert_eq!(full.insert(&U256::MAX), None);
+        assert_eq!(
+            FullMerkleTree::from_indexed_leaves(20, vec![(0, U256::one()), (1, U256::MAX)]),
+            None
+        );
+        assert_eq!(full.get_last_root(), MerkleTreeWithHistory::new(20).get_last_root());
+    }
+
+    #[test]
+    fn test_verify_path() {
+        let mut mt = MerkleTreeWithHistory::new(20);
+        mt.insert(&U256::from(42_u32));
+        let leaf = U256::from(7_u32);
+        let (_, path_indices, path_elements) = mt.insert_and_return_path(&leaf).unwrap();
+        let root = mt.get_last_root();
+        assert!(verify_path(&leaf, &path_elements, &path_indices, &root));
+
+        // Wrong leaf, root, side or selector value
+        assert!(!verify_path(&U256::from(8_u32), &path_elements, &path_indices, &root));
+        assert!(!verify_path(&leaf, &path_elements, &path_indices, &U256::from(1_u32)));
+        let mut flipped = path_indices.clone();
+        flipped[0] = U256::zero();
+        assert!(!verify_path(&leaf, &path_elements, &flipped, &root));
+        let mut invalid = path_indices.clone();
+        invalid[0] = U256::from(2_u32);
+        assert!(!verify_path(&leaf, &path_elements, &invalid, &root));
+        assert!(!verify_path(&leaf, &path_elements[1..], &path_indices, &root));
+    }
+
+
+    #[test]
+    fn test_full_merkletree_matches_merkletree() {
+        let mut mt = MerkleTreeWithHistory::new(20);
+        let mut full = FullMerkleTree::new(20);
+        assert_eq!(full.get_last_root(), mt.get_last_root());
+
+        let mut roots = vec![mt.get_last_root()];
+        for leaf in 1..=9_u32 {
+            let (index, path_indices, path_elements) =
+                mt.insert_and_return_path(&U256::from(leaf)).unwrap();
+            assert_eq!(full.insert(&U256::from(leaf)), Some(index));
+            assert_eq!(full.get_last_root(), mt.get_last_root());
+            assert_eq!(
+                full.get_path(index, &mt.get_last_root()),
+                Some((path_indices, path_elements))
+            );
+            roots.push(mt.get_last_root());
+        }
+
+        // Paths of old leaves against every later root hash up to that root
+        for (leaf_count, root) in roots.iter().enumerate() {
+            assert_eq!(full.leaf_count_of_root(root), Some(leaf_count as u32));
+            for index in 0..(leaf_count as u32) {
+                let (path_indices, path_elements) = full.get_path(index, root).unwrap();
+                assert!(verify_path(
+                    &full.leaves[index as usize],
+                    &path_elements,
+                    &path_indices,
+                    root
+                ));
+            }
+        }
+        assert_eq!(full.get_path(9, &roots[9]), None);
+        assert_eq!(full.get_path(0, &U256::from(1_u32)), None);
+    }
+
+    #[test]
+    fn test_full_merkletree_from_indexed_leaves() {
+        let full = FullMerkleTree::from_leaves(20, vec![U256::from(42_u32), U256::from(7_u32)])
+            .unwrap();
+        let rebuilt = FullMerkleTree::from_indexed_leaves(
+            20,
+            vec![(1, U256::from(7_u32)), (0, U256::from(42_u32))],
+        )
+        .unwrap();
+        assert_eq!(rebuilt, full);
+
+        // A missing index can't be rebuilt
+        assert_eq!(
+            FullMerkleTree::from_indexed_leaves(20, vec![(1, U256::from(7_u32))]),
+            None
+        );
+        assert_eq!(
+            FullMerkleTree::from_leaves(1, vec![U256::zero(), U256::zero(), U256::zero()]),
+            None
+        );
+    }
+
     }
@@ -126,11 +126,9 @@ impl Deposit {
         let right = U256::zero();
 
         let nullifier = U256::from_str(&self.nullifier).unwrap();
-        let inputs = vec![nullifier.to_le_bytes(), right.to_le_bytes()];
+        let res = poseidon.hash_u256(&[nullifier, right]).unwrap();
 
-        let res = poseidon.hash(inputs).unwrap();
-
-        U256::from_le_bytes(res).to_string()
+        res.to_string()
     }
 
     pub fn get_nullifier_hash(self, leaf_index: u128) -> String {
@@ -141,15 +139,9 @@ impl Deposit {
         let secret = U256::from(1_u16);
         let leaf_i = U256::from(leaf_index);
 
-        let inputs = vec![
-            nullifier.to_le_bytes(),
-            secret.to_le_bytes(),
-            leaf_i.to_le_bytes(),
-        ];
-
-        let res = poseidon.hash(inputs).unwrap();
+        let res = poseidon.hash_u256(&[nullifier, secret, leaf_i]).unwrap();
 
-        U256::from_le_bytes(res).to_string()
+        res.to_string()
     }
 
     pub fn commitment_as_array(self) -> [u8; 32] {
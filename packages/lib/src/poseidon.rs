@@ -1,42 +1,244 @@
 pub use self::poseidon::Poseidon;
 
-mod hasher {
-    use ark_crypto_primitives::Error;
-    use ark_ff::{BigInteger, PrimeField};
-    use ark_std::{marker::PhantomData, vec::Vec};
-    use arkworks_native_gadgets::poseidon::FieldHasher;
-    use arkworks_native_gadgets::poseidon::Poseidon;
-    use arkworks_native_gadgets::poseidon::PoseidonParameters;
-    use arkworks_native_gadgets::to_field_elements;
-    pub struct ArkworksPoseidonHasher<F: PrimeField>(PhantomData<F>);
-
-    impl<F: PrimeField> ArkworksPoseidonHasher<F> {
-        pub fn hash(input: &[u8], param_bytes: &[u8]) -> Result<Vec<u8>, Error> {
-            let params = PoseidonParameters::<F>::from_bytes(param_bytes)?;
-            let poseidon = Poseidon::new(params);
-
-            let input_f = to_field_elements::<F>(input)?;
-            let output: F = poseidon.hash(&input_f)?;
-            let value = output.into_repr().to_bytes_le();
-            Ok(value)
-        }
-    }
-
-    use ark_bn254::Fr as Bn254;
-    pub type ArkworksPoseidonHasherBn254 = ArkworksPoseidonHasher<Bn254>;
+/// Poseidon over BN254 with x^5 S-boxes and circomlib's constants, for the widths the
+/// arkworks setups don't cover. Constants come from the Grain LFSR of the reference
+/// `generate_parameters_grain.sage`, as circomlib's do.
+mod circom {
+    use ark_bn254::Fr;
+    use ark_ff::{BigInteger256, Field, PrimeField, Zero};
+    use lazy_static::lazy_static;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    const FIELD_SIZE: u32 = 254;
+    const FULL_ROUNDS: usize = 8;
+    // circomlib's partial rounds for widths 2 to 17
+    const PARTIAL_ROUNDS: [usize; 16] = [
+        56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68,
+    ];
+
+    pub const MAX_WIDTH: usize = 17;
+
+    pub struct Params {
+        round_constants: Vec<Fr>,
+        mds: Vec<Vec<Fr>>,
+        partial_rounds: usize,
+    }
+
+    lazy_static! {
+        // Generated on first use of each width
+        static ref PARAMS: Mutex<BTreeMap<usize, Arc<Params>>> = Mutex::new(BTreeMap::new());
+    }
+
+    /// Parameters for `width` (inputs + 1), between 2 and `MAX_WIDTH`
+    pub fn params(width: usize) -> Arc<Params> {
+        let mut params = PARAMS.lock().unwrap();
+        params
+            .entry(width)
+            .or_insert_with(|| Arc::new(generate(width)))
+            .clone()
+    }
+
+    // 80-bit shift register, bit i holding the i-th oldest bit
+    struct Grain {
+        state: u128,
+    }
+
+    impl Grain {
+        fn new(width: usize, partial_rounds: usize) -> Self {
+            // Field type (1 = prime field), S-box (0 = x^alpha), field size, width and
+            // round counts, followed by 30 ones
+            let fields = [
+                (1, 2),
+                (0, 4),
+                (FIELD_SIZE as u128, 12),
+                (width as u128, 12),
+                (FULL_ROUNDS as u128, 10),
+                (partial_rounds as u128, 10),
+            ];
+            let mut state = 0u128;
+            let mut position = 0;
+            for (value, len) in fields.iter() {
+                for i in (0..*len).rev() {
+                    state |= ((value >> i) & 1) << position;
+                    position += 1;
+                }
+            }
+            for _ in 0..30 {
+                state |= 1 << position;
+                position += 1;
+            }
+
+            let mut grain = Self { state };
+            for _ in 0..160 {
+                grain.step();
+            }
+            grain
+        }
+
+        fn step(&mut self) -> u128 {
+            let s = self.state;
+            let bit = ((s >> 62) ^ (s >> 51) ^ (s >> 38) ^ (s >> 23) ^ (s >> 13) ^ s) & 1;
+            self.state = (s >> 1) | (bit << 79);
+            bit
+        }
+
+        // Bits come in pairs, and the second one is kept only when the first is set
+        fn next_bit(&mut self) -> u64 {
+            loop {
+                let first = self.step();
+                let second = self.step();
+                if first == 1 {
+                    return second as u64;
+                }
+            }
+        }
+
+        // `FIELD_SIZE` bits, most significant first
+        fn next_bigint(&mut self) -> BigInteger256 {
+            let mut limbs = [0u64; 4];
+            for weight in (0..FIELD_SIZE as usize).rev() {
+                limbs[weight / 64] |= self.next_bit() << (weight % 64);
+            }
+            BigInteger256::new(limbs)
+        }
+
+        // Uniform field element, by rejecting values past the modulus
+        fn next_field_element(&mut self) -> Fr {
+            loop {
+                if let Some(element) = Fr::from_repr(self.next_bigint()) {
+                    return element;
+                }
+            }
+        }
+
+        // Field element taken modulo the field order
+        fn next_reduced_field_element(&mut self) -> Fr {
+            let bigint = self.next_bigint();
+            let bytes: Vec<u8> = bigint
+                .0
+                .iter()
+                .flat_map(|limb| limb.to_le_bytes().to_vec())
+                .collect();
+            Fr::from_le_bytes_mod_order(&bytes)
+        }
+    }
+
+    fn generate(width: usize) -> Params {
+        let partial_rounds = PARTIAL_ROUNDS[width - 2];
+        let mut grain = Grain::new(width, partial_rounds);
+
+        let round_constants = (0..(FULL_ROUNDS + partial_rounds) * width)
+            .map(|_| grain.next_field_element())
+            .collect();
+
+        // Cauchy matrix 1 / (x_i + y_j) over distinct x and y
+        let mds = loop {
+            let elements: Vec<Fr> = (0..2 * width)
+                .map(|_| grain.next_reduced_field_element())
+                .collect();
+            let distinct = elements
+                .iter()
+                .enumerate()
+                .all(|(i, a)| elements[i + 1..].iter().all(|b| a != b));
+            if !distinct {
+                continue;
+            }
+            let (xs, ys) = elements.split_at(width);
+            if xs.iter().any(|x| ys.iter().any(|y| (*x + y).is_zero())) {
+                continue;
+            }
+            break xs
+                .iter()
+                .map(|x| ys.iter().map(|y| (*x + y).inverse().unwrap()).collect())
+                .collect::<Vec<Vec<Fr>>>();
+        };
+
+        Params {
+            round_constants,
+            mds,
+            partial_rounds,
+        }
+    }
+
+    fn sbox(x: &mut Fr) {
+        let x2 = x.square();
+        *x *= x2.square();
+    }
+
+    /// Applies the permutation to `state`, whose length is the width
+    pub fn permute(params: &Params, state: &mut Vec<Fr>) {
+        let width = state.len();
+        let half_full_rounds = FULL_ROUNDS / 2;
+        for round in 0..(FULL_ROUNDS + params.partial_rounds) {
+            for (i, element) in state.iter_mut().enumerate() {
+                *element += params.round_constants[round * width + i];
+            }
+            if round < half_full_rounds || round >= half_full_rounds + params.partial_rounds {
+                state.iter_mut().for_each(sbox);
+            } else {
+                sbox(&mut state[0]);
+            }
+            *state = params
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(state.iter()).map(|(m, s)| *m * s).sum())
+                .collect();
+        }
+    }
+
+    /// Field element of little-endian bytes, if they're below the modulus
+    pub fn to_field_element(bytes: &[u8; 32]) -> Option<Fr> {
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+            let mut limb_bytes = [0u8; 8];
+            limb_bytes.copy_from_slice(chunk);
+            *limb = u64::from_le_bytes(limb_bytes);
+        }
+        Fr::from_repr(BigInteger256::new(limbs))
+    }
+
+    pub fn to_bytes(element: &Fr) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.chunks_mut(8).zip(element.into_repr().0.iter()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
 }
 
 #[allow(clippy::all)]
 pub mod poseidon {
-    use ark_bn254::Fr as Bn254Fr;
+    pub use ark_bn254::Fr as Bn254Fr;
+    use ark_ff::Zero;
+    use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon as ArkworksPoseidon};
     use arkworks_setups::common::setup_params;
     use arkworks_setups::Curve;
+    use lazy_static::lazy_static;
     use serde::{Deserialize, Serialize};
 
     use cosmwasm_std::Uint256 as U256;
 
-    use super::hasher::ArkworksPoseidonHasherBn254;
+    use super::circom;
+
+    // Round constants and MDS matrices are derived once per width and shared by every
+    // `Poseidon`. Deriving them took far longer than hashing.
+    lazy_static! {
+        static ref POSEIDON_WIDTH_3: ArkworksPoseidon<Bn254Fr> =
+            ArkworksPoseidon::new(setup_params::<Bn254Fr>(Curve::Bn254, 5, 3));
+        static ref POSEIDON_WIDTH_4: ArkworksPoseidon<Bn254Fr> =
+            ArkworksPoseidon::new(setup_params::<Bn254Fr>(Curve::Bn254, 5, 4));
+        static ref POSEIDON_WIDTH_5: ArkworksPoseidon<Bn254Fr> =
+            ArkworksPoseidon::new(setup_params::<Bn254Fr>(Curve::Bn254, 5, 5));
+        static ref PARAMS_BYTES: [Vec<u8>; 3] = [
+            POSEIDON_WIDTH_3.params.to_bytes(),
+            POSEIDON_WIDTH_4.params.to_bytes(),
+            POSEIDON_WIDTH_5.params.to_bytes(),
+        ];
+    }
 
+    // Still carries the serialized parameters so that stored values keep their shape,
+    // but hashes with the cached ones
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Poseidon {
         hasher_params_width_3_bytes: Vec<u8>,
@@ -51,17 +253,36 @@ pub mod poseidon {
         HashError,
         /// Invalid hash width
         InvalidHashInputWidth,
+        /// Returned if an input is not below the field modulus
+        InputOutOfField,
     }
 
     /// The Hash result type.
     pub type Result<T> = core::result::Result<T, Error>;
 
+    /// Most inputs `hash` takes, like circomlib's `Poseidon(n)`
+    pub const MAX_INPUTS: usize = circom::MAX_WIDTH - 1;
+
+    /// Field element of little-endian bytes. Values at or above the modulus are
+    /// rejected rather than reduced, since the circuits would see a different input.
+    pub fn bytes_to_fr(bytes: &[u8; 32]) -> Result<Bn254Fr> {
+        circom::to_field_element(bytes).ok_or(Error::InputOutOfField)
+    }
+
+    pub fn u256_to_fr(value: &U256) -> Result<Bn254Fr> {
+        bytes_to_fr(&value.to_le_bytes())
+    }
+
+    pub fn fr_to_u256(element: &Bn254Fr) -> U256 {
+        U256::from_le_bytes(circom::to_bytes(element))
+    }
+
     impl Poseidon {
         pub fn new() -> Self {
             Self {
-                hasher_params_width_3_bytes: setup_params::<Bn254Fr>(Curve::Bn254, 5, 3).to_bytes(),
-                hasher_params_width_4_bytes: setup_params::<Bn254Fr>(Curve::Bn254, 5, 4).to_bytes(),
-                hasher_params_width_5_bytes: setup_params::<Bn254Fr>(Curve::Bn254, 5, 5).to_bytes(),
+                hasher_params_width_3_bytes: PARAMS_BYTES[0].clone(),
+                hasher_params_width_4_bytes: PARAMS_BYTES[1].clone(),
+                hasher_params_width_5_bytes: PARAMS_BYTES[2].clone(),
             }
         }
 
@@ -71,36 +292,70 @@ pub mod poseidon {
             Ok(U256::from_le_bytes(res))
         }
 
+        /// Inputs at or above the modulus fail with `HashError`, as they always have.
+        /// `hash_u256` tells them apart as `InputOutOfField`
         pub fn hash(&self, inputs: Vec<[u8; 32]>) -> Result<[u8; 32]> {
-            let num_inputs = inputs.len();
-            let mut packed_inputs = Vec::new();
-            for inp in inputs {
-                packed_inputs.extend_from_slice(&inp);
-            }
+            let inputs = inputs
+                .iter()
+                .map(|inp| bytes_to_fr(inp).map_err(|_| Error::HashError))
+                .collect::<Result<Vec<_>>>()?;
 
-            let hash_result = match num_inputs {
-                2 => ArkworksPoseidonHasherBn254::hash(
-                    &packed_inputs,
-                    &self.hasher_params_width_3_bytes,
-                ),
-                3 => ArkworksPoseidonHasherBn254::hash(
-                    &packed_inputs,
-                    &self.hasher_params_width_4_bytes,
-                ),
-                4 => ArkworksPoseidonHasherBn254::hash(
-                    &packed_inputs,
-                    &self.hasher_params_width_5_bytes,
-                ),
+            Ok(circom::to_bytes(&self.hash_fr(&inputs)?))
+        }
+
+        /// Hashes `Uint256` inputs, which must be below the field modulus
+        pub fn hash_u256(&self, inputs: &[U256]) -> Result<U256> {
+            let inputs = inputs.iter().map(u256_to_fr).collect::<Result<Vec<_>>>()?;
+
+            Ok(fr_to_u256(&self.hash_fr(&inputs)?))
+        }
+
+        /// Hashes 1 to `MAX_INPUTS` field elements
+        pub fn hash_fr(&self, inputs: &[Bn254Fr]) -> Result<Bn254Fr> {
+            let poseidon: &ArkworksPoseidon<Bn254Fr> = match inputs.len() {
+                2 => &POSEIDON_WIDTH_3,
+                3 => &POSEIDON_WIDTH_4,
+                4 => &POSEIDON_WIDTH_5,
+                1 | 5..=MAX_INPUTS => {
+                    let mut state = vec![Bn254Fr::zero()];
+                    state.extend_from_slice(inputs);
+                    circom::permute(&circom::params(state.len()), &mut state);
+                    return Ok(state[0]);
+                }
                 _ => return Err(Error::InvalidHashInputWidth),
             };
 
-            hash_result
-                .map(|h| {
-                    let mut hash_result = [0u8; 32];
-                    hash_result.copy_from_slice(&h);
-                    hash_result
-                })
-                .map_err(|_| Error::HashError)
+            poseidon.hash(inputs).map_err(|_| Error::HashError)
+        }
+
+        /// Hashes any number of inputs with a width-17 sponge. The capacity element
+        /// starts as the number of inputs, then each chunk of up to 16 inputs is added
+        /// to the other elements before a permutation. The result is the capacity
+        /// element. Up to 16 inputs, this is circomlib's `PoseidonEx(16, 1)` with the
+        /// number of inputs as `initialState` and zeros for missing inputs. Longer inputs
+        /// carry the whole state from one permutation to the next, which no circomlib
+        /// template does.
+        pub fn hash_sponge(&self, inputs: Vec<[u8; 32]>) -> Result<[u8; 32]> {
+            let mut state = vec![Bn254Fr::zero(); circom::MAX_WIDTH];
+            state[0] = Bn254Fr::from(inputs.len() as u64);
+
+            let params = circom::params(circom::MAX_WIDTH);
+            let mut chunks: Vec<&[[u8; 32]]> = inputs.chunks(MAX_INPUTS).collect();
+            if chunks.is_empty() {
+                chunks.push(&[]);
+            }
+            for chunk in chunks {
+                for (element, inp) in state[1..].iter_mut().zip(chunk.iter()) {
+                    *element += bytes_to_fr(inp).map_err(|_| Error::HashError)?;
+                }
+                circom::permute(&params, &mut state);
+            }
+
+            Ok(circom::to_bytes(&state[0]))
+        }
+
+        pub fn hash_sponge_as_u256(&self, inputs: Vec<[u8; 32]>) -> Result<U256> {
+            Ok(U256::from_le_bytes(self.hash_sponge(inputs)?))
         }
     }
 
@@ -127,5 +382,244 @@ mod tests {
 
         let h = hasher.hash(inputs).unwrap();
         let n = U256::from_le_bytes(h);
+        assert_eq!(
+            n,
+            crate::bignum!(
+                "7853200120776062878684798364095072458815029376092732009249414926327459813530"
+            )
+        );
+    }
+
+    #[test]
+    fn test_poseidon_cached_params_match_setup() {
+        // Hashing with the cached parameters gives the same result as setting them up per hash
+        use arkworks_native_gadgets::poseidon::{
+            FieldHasher, Poseidon as ArkworksPoseidon, PoseidonParameters,
+        };
+        use arkworks_setups::common::setup_params;
+        use arkworks_setups::Curve;
+
+        let inputs = vec![
+            ark_bn254::Fr::from(1_u64),
+            ark_bn254::Fr::from(2_u64),
+            ark_bn254::Fr::from(3_u64),
+        ];
+        let params = setup_params::<ark_bn254::Fr>(Curve::Bn254, 5, 4).to_bytes();
+        let fresh = ArkworksPoseidon::new(PoseidonParameters::from_bytes(&params).unwrap());
+
+        assert_eq!(
+            Poseidon::new().hash_fr(&inputs).unwrap(),
+            fresh.hash(&inputs).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_poseidon_serde_shape() {
+        // Same fields and parameters as a `Poseidon` serialized before the cache
+        use arkworks_setups::common::setup_params;
+        use arkworks_setups::Curve;
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StoredPoseidon {
+            hasher_params_width_3_bytes: Vec<u8>,
+            hasher_params_width_4_bytes: Vec<u8>,
+            hasher_params_width_5_bytes: Vec<u8>,
+        }
+
+        let json = serde_json::to_string(&Poseidon::new()).unwrap();
+        let stored: StoredPoseidon = serde_json::from_str(&json).unwrap();
+        let params = |width| setup_params::<ark_bn254::Fr>(Curve::Bn254, 5, width).to_bytes();
+        assert_eq!(stored.hasher_params_width_3_bytes, params(3));
+        assert_eq!(stored.hasher_params_width_4_bytes, params(4));
+        assert_eq!(stored.hasher_params_width_5_bytes, params(5));
+
+        let hasher: Poseidon = serde_json::from_str(&json).unwrap();
+        assert!(hasher.hash(inputs(1..=2)).is_ok());
+    }
+
+    fn inputs(values: impl Iterator<Item = u16>) -> Vec<[u8; 32]> {
+        values.map(|v| U256::from(v).to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_poseidon_circomlib_vectors() {
+        // circomlib's Poseidon(n) of [1, ..., n]
+        let expected = [
+            "18586133768512220936620570745912940619677854269274689475585506675881198879027",
+            "7853200120776062878684798364095072458815029376092732009249414926327459813530",
+            "6542985608222806190361240322586112750744169038454362455181422643027100751666",
+            "18821383157269793795438455681495246036402687001665670618754263018637548127333",
+            "6183221330272524995739186171720101788151706631170188140075976616310159254464",
+            "20400040500897583745843009878988256314335038853985262692600694741116813247201",
+            "12748163991115452309045839028154629052133952896122405799815156419278439301912",
+            "18604317144381847857886385684060986177838410221561136253933256952257712543953",
+            "13589767895268936107593642967621470491511464502761040466226072462545218539640",
+            "3657500514307717306974218405144578736633140001277925127187636780142269815841",
+            "3572015662710076994097916907865950486270383304442561406230608893458731714472",
+            "2501997477381648492950318384533644783248002172679259592360114615426357826485",
+            "7041832639553862712666971417715061873827921493498355005117622707743491651590",
+            "8354478399926161176778659061636406690034081872658507739535256090879947077494",
+            "4203130618016961831408770638653325366880478848856764494148034853759773445968",
+            "9989051620750914585850546081941653841776809718687451684622678807385399211877",
+        ];
+        let hasher = Poseidon::new();
+        for (n, hash) in (1..).zip(expected.iter()) {
+            let h = hasher.hash_as_u256(inputs(1..=n)).unwrap();
+            assert_eq!(h, crate::bignum!(hash), "{} inputs", n);
+        }
+    }
+
+    #[test]
+    fn test_poseidon_circomlib_known_answers() {
+        // circomlib's Poseidon(1), Poseidon(2) and Poseidon(3) (widths 2 to 4), which the
+        // circuits use for commitments, nullifiers and tree nodes
+        let max = crate::bignum!(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495616"
+        );
+        let zero_value = crate::bignum!(
+            "21663839004416932945382355908790599225266501822907911457504978515578255421292"
+        );
+        let cases = vec![
+            (
+                vec![U256::zero()],
+                "19014214495641488759237505126948346942972912379615652741039992445865937985820",
+            ),
+            (
+                vec![U256::zero(); 2],
+                "14744269619966411208579211824598458697587494354926760081771325075741142829156",
+            ),
+            (
+                vec![U256::zero(); 3],
+                "5317387130258456662214331362918410991734007599705406860481038345552731150762",
+            ),
+            (
+                vec![max],
+                "3366645945435192953002076803303112651887535928162668198103357554665518664470",
+            ),
+            (
+                vec![max, max - U256::from(1_u16)],
+                "6149179154952470170078400310622041627793456726695327690341847329306121599910",
+            ),
+            (
+                vec![max, max - U256::from(1_u16), max - U256::from(2_u16)],
+                "20133197287301041945818928843432871492701166386418821647132231623255907241873",
+            ),
+            (
+                vec![zero_value, zero_value],
+                "8995896153219992062710898675021891003404871425075198597897889079729967997688",
+            ),
+        ];
+
+        let hasher = Poseidon::new();
+        for (values, expected) in cases {
+            let inputs = values.iter().map(|v| v.to_le_bytes()).collect();
+            assert_eq!(
+                hasher.hash_as_u256(inputs).unwrap(),
+                crate::bignum!(expected),
+                "{:?}",
+                values
+            );
+        }
+    }
+
+    #[test]
+    fn test_poseidon_inputs_past_modulus() {
+        // circomlib would reduce these, so they're rejected rather than hashed differently.
+        // `hash` keeps failing with `HashError` like it did before `hash_u256`
+        let modulus = crate::bignum!(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+        );
+        let hasher = Poseidon::new();
+        assert!(matches!(
+            hasher.hash(vec![modulus.to_le_bytes()]),
+            Err(poseidon::Error::HashError)
+        ));
+        assert!(matches!(
+            hasher.hash(vec![modulus.to_le_bytes(), U256::zero().to_le_bytes()]),
+            Err(poseidon::Error::HashError)
+        ));
+        assert!(matches!(
+            hasher.hash_u256(&[modulus]),
+            Err(poseidon::Error::InputOutOfField)
+        ));
+        assert!(matches!(
+            hasher.hash_u256(&[U256::zero(), U256::MAX]),
+            Err(poseidon::Error::InputOutOfField)
+        ));
+        assert!(hasher
+            .hash_u256(&[U256::zero(), modulus - U256::from(1_u16)])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_poseidon_hash_u256_matches_hash_fr() {
+        let hasher = Poseidon::new();
+        let values = [U256::from(1_u16), U256::from(2_u16)];
+        let elements: Vec<_> = values
+            .iter()
+            .map(|v| poseidon::u256_to_fr(v).unwrap())
+            .collect();
+
+        let hash = hasher.hash_u256(&values).unwrap();
+        assert_eq!(
+            hash,
+            poseidon::fr_to_u256(&hasher.hash_fr(&elements).unwrap())
+        );
+        assert_eq!(
+            hash,
+            hasher
+                .hash_as_u256(values.iter().map(|v| v.to_le_bytes()).collect())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_poseidon_invalid_width() {
+        let hasher = Poseidon::new();
+        assert!(hasher.hash(vec![]).is_err());
+        assert!(hasher.hash(inputs(1..=17)).is_err());
+    }
+
+    #[test]
+    fn test_poseidon_generated_params_match_setup() {
+        // The Grain constants agree with the arkworks ones where both exist
+        use ark_ff::Zero;
+
+        let mut state = vec![ark_bn254::Fr::zero()];
+        for inp in inputs(1..=3) {
+            state.push(circom::to_field_element(&inp).unwrap());
+        }
+        circom::permute(&circom::params(4), &mut state);
+
+        assert_eq!(
+            circom::to_bytes(&state[0]),
+            Poseidon::new().hash(inputs(1..=3)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_poseidon_sponge() {
+        // From a reference implementation of the width-17 permutation. The first two
+        // are also circomlib's `PoseidonEx(16, 1)` outputs, as they take one permutation.
+        let hasher = Poseidon::new();
+        assert_eq!(
+            hasher.hash_sponge_as_u256(vec![]).unwrap(),
+            crate::bignum!(
+                "6961025786505490270790487869888725702980364259855350215456397845563605340881"
+            )
+        );
+        assert_eq!(
+            hasher.hash_sponge_as_u256(inputs(1..=5)).unwrap(),
+            crate::bignum!(
+                "10702687222980156297452412847248631036018473173869860624510675441105536254801"
+            )
+        );
+        assert_eq!(
+            hasher.hash_sponge_as_u256(inputs(1..=20)).unwrap(),
+            crate::bignum!(
+                "4433755629480249177589433372085986620395340475019478912619569697798890550637"
+            )
+        );
     }
 }
@@ -0,0 +1,589 @@
+use cosmwasm_std::{StdError, StdResult, Storage, Uint256 as U256};
+use cw_storage_plus::Map;
+use serde::{Deserialize, Serialize};
+
+use crate::poseidon::Poseidon;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SmtLeaf {
+    pub key: U256,
+    pub value: U256,
+}
+
+/// Membership or non-membership proof, in the inputs circomlib's `SMTVerifier` takes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SmtProof {
+    pub root: U256,
+    // Siblings from the root down to the node the key's path ends at. The last one is
+    // never zero, so the circuit finds the leaf depth once they're padded with zeros.
+    pub siblings: Vec<U256>,
+    // Leaf the key's path ends at, if the path doesn't end at an empty node
+    pub leaf: Option<SmtLeaf>,
+}
+
+/// Inputs of `SparseMerkleTreeChecker(levels)` in `circuits/lib/sparseMerkleTree.circom`.
+/// `fnc` is 0 to check that `key` holds `value` and 1 to check that `key` isn't in the tree.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SmtCheckerInputs {
+    pub root: U256,
+    pub key: U256,
+    pub value: U256,
+    pub fnc: U256,
+    pub oldKey: U256,
+    pub oldValue: U256,
+    pub isOld0: U256,
+    pub siblings: Vec<U256>,
+}
+
+impl SmtCheckerInputs {
+    /// Returns `None` if the proof has more siblings than a checker of `levels` takes
+    pub fn new(proof: &SmtProof, key: U256, value: U256, fnc: u32, levels: u32) -> Option<Self> {
+        if proof.siblings.len() >= levels as usize {
+            return None;
+        }
+        let (old_key, old_value) = proof
+            .leaf
+            .as_ref()
+            .map_or((U256::zero(), U256::zero()), |leaf| (leaf.key, leaf.value));
+        let mut siblings = proof.siblings.clone();
+        siblings.resize(levels as usize, U256::zero());
+
+        Some(Self {
+            root: proof.root,
+            key,
+            value,
+            fnc: U256::from(fnc),
+            oldKey: old_key,
+            oldValue: old_value,
+            isOld0: U256::from(proof.leaf.is_none() as u32),
+            siblings,
+        })
+    }
+}
+
+/// Poseidon sparse Merkle trees kept in contract storage, laid out like circomlib's
+/// `smt.js` so its `SMTVerifier` checks their proofs. Bit `i` of a key picks the child
+/// taken at depth `i`, lowest bit first. A subtree with a single key in it is that key's
+/// leaf, `Poseidon(key, value, 1)`, so leaves sit as high as they can and keys sharing
+/// their lowest bits push each other down. Other nodes are `Poseidon(left, right)`, or
+/// zero when empty. Only non-empty nodes are stored. Several trees can share one store,
+/// told apart by `tree_id`.
+pub struct SparseMerkleTreeStore<'a> {
+    levels: Map<'a, u32, u32>,
+    // Non-empty nodes, keyed by (tree id, depth, key bits below the depth)
+    nodes: Map<'a, (u32, u32, String), U256>,
+    // Leaves, keyed like their nodes
+    leaves: Map<'a, (u32, u32, String), SmtLeaf>,
+}
+
+impl<'a> SparseMerkleTreeStore<'a> {
+    pub const fn new(
+        levels_namespace: &'a str,
+        nodes_namespace: &'a str,
+        leaves_namespace: &'a str,
+    ) -> Self {
+        Self {
+            levels: Map::new(levels_namespace),
+            nodes: Map::new(nodes_namespace),
+            leaves: Map::new(leaves_namespace),
+        }
+    }
+
+    /// Creates an empty tree, whose root is zero. Proofs fit a `SMTVerifier(levels)`, so
+    /// leaves are at most `levels - 1` deep and keys sharing their lowest `levels - 1`
+    /// bits can't both be in the tree. Keys are field elements, so `levels` is at most
+    /// 254, which only rules out such pairs past 2^253.
+    pub fn init(&self, storage: &mut dyn Storage, tree_id: u32, levels: u32) -> StdResult<()> {
+        if levels == 0 || levels > 254 {
+            return Err(StdError::generic_err("Invalid tree levels"));
+        }
+        if self.levels.has(storage, tree_id) {
+            return Err(StdError::generic_err("Tree already exists"));
+        }
+        self.levels.save(storage, tree_id, &levels)
+    }
+
+    pub fn levels(&self, storage: &dyn Storage, tree_id: u32) -> StdResult<u32> {
+        self.levels.load(storage, tree_id)
+    }
+
+    pub fn get_root(&self, storage: &dyn Storage, tree_id: u32) -> StdResult<U256> {
+        self.levels.load(storage, tree_id)?;
+        self.node(storage, tree_id, 0, &U256::zero())
+    }
+
+    /// Value of `key`, if it's in the tree
+    pub fn get(&self, storage: &dyn Storage, tree_id: u32, key: &U256) -> StdResult<Option<U256>> {
+        let (_, leaf, _) = self.find(storage, tree_id, key)?;
+        Ok(leaf.filter(|leaf| leaf.key == *key).map(|leaf| leaf.value))
+    }
+
+    /// Inserts `key` or updates its value
+    pub fn insert(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        key: &U256,
+        value: &U256,
+    ) -> StdResult<()> {
+        let levels = self.levels.load(storage, tree_id)?;
+        let leaf = SmtLeaf {
+            key: *key,
+            value: *value,
+        };
+        let leaf_hash = hash_leaf(&leaf)?;
+
+        let (depth, found, _) = self.find(storage, tree_id, key)?;
+        let leaf_depth = match found {
+            // Both keys move down to where their bits first differ
+            Some(other) if other.key != *key => {
+                let split = (depth..levels - 1)
+                    .find(|i| bit(key, *i) != bit(&other.key, *i))
+                    .ok_or_else(|| {
+                        StdError::generic_err("Key shares too many low bits with another key")
+                    })?;
+                self.remove_leaf(storage, tree_id, depth, key);
+                self.save_leaf(storage, tree_id, split + 1, &other, &hash_leaf(&other)?)?;
+                split + 1
+            }
+            _ => depth,
+        };
+
+        self.save_leaf(storage, tree_id, leaf_depth, &leaf, &leaf_hash)?;
+        self.update_path(storage, tree_id, key, leaf_depth)
+    }
+
+    pub fn delete(&self, storage: &mut dyn Storage, tree_id: u32, key: &U256) -> StdResult<()> {
+        let (depth, found, _) = self.find(storage, tree_id, key)?;
+        match found {
+            Some(leaf) if leaf.key == *key => (),
+            _ => return Err(StdError::generic_err("Key is not in the tree")),
+        }
+        self.remove_leaf(storage, tree_id, depth, key);
+        if depth == 0 {
+            return Ok(());
+        }
+
+        // A leaf left alone next to the deleted one moves up for as long as it has no
+        // sibling, replacing the nodes on its way
+        let sibling = self.leaves.may_load(
+            storage,
+            (tree_id, depth, sibling_prefix(key, depth - 1).to_string()),
+        )?;
+        let top = match sibling {
+            Some(sibling) => {
+                let mut top = depth - 1;
+                while top > 0
+                    && self
+                        .node(storage, tree_id, top, &sibling_prefix(key, top - 1))?
+                        .is_zero()
+                {
+                    top -= 1;
+                }
+                self.remove_leaf(storage, tree_id, depth, &sibling.key);
+                for level in top..depth {
+                    self.nodes
+                        .remove(storage, (tree_id, level, prefix(key, level).to_string()));
+                }
+                self.save_leaf(storage, tree_id, top, &sibling, &hash_leaf(&sibling)?)?;
+                top
+            }
+            None => depth,
+        };
+        self.update_path(storage, tree_id, key, top)
+    }
+
+    /// Proof for `key` against the current root. It proves membership if the key is in
+    /// the tree and non-membership otherwise.
+    pub fn prove(&self, storage: &dyn Storage, tree_id: u32, key: &U256) -> StdResult<SmtProof> {
+        let (_, leaf, siblings) = self.find(storage, tree_id, key)?;
+        Ok(SmtProof {
+            root: self.node(storage, tree_id, 0, &U256::zero())?,
+            siblings,
+            leaf,
+        })
+    }
+
+    // Follows the path of `key` down to a leaf or an empty node, and returns its depth,
+    // the leaf and the siblings on the way
+    fn find(
+        &self,
+        storage: &dyn Storage,
+        tree_id: u32,
+        key: &U256,
+    ) -> StdResult<(u32, Option<SmtLeaf>, Vec<U256>)> {
+        let levels = self.levels.load(storage, tree_id)?;
+        let mut siblings = vec![];
+        let mut depth = 0;
+        loop {
+            let node_prefix = prefix(key, depth).to_string();
+            if let Some(leaf) = self
+                .leaves
+                .may_load(storage, (tree_id, depth, node_prefix))?
+            {
+                return Ok((depth, Some(leaf), siblings));
+            }
+            if depth + 1 >= levels
+                || self
+                    .node(storage, tree_id, depth, &prefix(key, depth))?
+                    .is_zero()
+            {
+                return Ok((depth, None, siblings));
+            }
+            siblings.push(self.node(storage, tree_id, depth + 1, &sibling_prefix(key, depth))?);
+            depth += 1;
+        }
+    }
+
+    fn node(
+        &self,
+        storage: &dyn Storage,
+        tree_id: u32,
+        depth: u32,
+        prefix: &U256,
+    ) -> StdResult<U256> {
+        Ok(self
+            .nodes
+            .may_load(storage, (tree_id, depth, prefix.to_string()))?
+            .unwrap_or_default())
+    }
+
+    fn save_leaf(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        depth: u32,
+        leaf: &SmtLeaf,
+        leaf_hash: &U256,
+    ) -> StdResult<()> {
+        let key = (tree_id, depth, prefix(&leaf.key, depth).to_string());
+        self.leaves.save(storage, key.clone(), leaf)?;
+        self.nodes.save(storage, key, leaf_hash)
+    }
+
+    fn remove_leaf(&self, storage: &mut dyn Storage, tree_id: u32, depth: u32, key: &U256) {
+        let key = (tree_id, depth, prefix(key, depth).to_string());
+        self.leaves.remove(storage, key.clone());
+        self.nodes.remove(storage, key);
+    }
+
+    // Rehashes the nodes above depth `depth` on the path of `key`, which all have at
+    // least two keys under them
+    fn update_path(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        key: &U256,
+        depth: u32,
+    ) -> StdResult<()> {
+        let poseidon = Poseidon::new();
+        for level in (0..depth).rev() {
+            let node_prefix = prefix(key, level);
+            let left = self.node(storage, tree_id, level + 1, &node_prefix)?;
+            let right = self.node(
+                storage,
+                tree_id,
+                level + 1,
+                &(node_prefix + (U256::one() << level)),
+            )?;
+            let node = hash_node(&poseidon, &left, &right)?;
+            self.nodes
+                .save(storage, (tree_id, level, node_prefix.to_string()), &node)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `key` holds `value` under `proof.root`
+pub fn verify_membership(proof: &SmtProof, key: &U256, value: &U256) -> bool {
+    match &proof.leaf {
+        Some(leaf) if leaf.key == *key && leaf.value == *value => match hash_leaf(leaf) {
+            Ok(leaf_hash) => compute_root(key, leaf_hash, &proof.siblings) == Some(proof.root),
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
+
+/// Checks that `key` isn't in the tree with root `proof.root`: its path ends at an empty
+/// node or at the leaf of another key
+pub fn verify_non_membership(proof: &SmtProof, key: &U256) -> bool {
+    let leaf_hash = match &proof.leaf {
+        None => U256::zero(),
+        Some(leaf) => {
+            let depth = proof.siblings.len() as u32;
+            if leaf.key == *key || depth >= 254 || prefix(&leaf.key, depth) != prefix(key, depth) {
+                return false;
+            }
+            match hash_leaf(leaf) {
+                Ok(leaf_hash) => leaf_hash,
+                Err(_) => return false,
+            }
+        }
+    };
+    compute_root(key, leaf_hash, &proof.siblings) == Some(proof.root)
+}
+
+fn compute_root(key: &U256, node: U256, siblings: &[U256]) -> Option<U256> {
+    if siblings.len() >= 254 || siblings.last().map_or(false, |sibling| sibling.is_zero()) {
+        return None;
+    }
+
+    let poseidon = Poseidon::new();
+    let mut current = node;
+    for (depth, sibling) in siblings.iter().enumerate().rev() {
+        current = if bit(key, depth as u32) {
+            hash_node(&poseidon, sibling, &current).ok()?
+        } else {
+            hash_node(&poseidon, &current, sibling).ok()?
+        };
+    }
+    Some(current)
+}
+
+// Lowest `depth` bits of `key`, which pick the node at that depth
+fn prefix(key: &U256, depth: u32) -> U256 {
+    *key % (U256::one() << depth)
+}
+
+// Prefix of the child at `depth + 1` that isn't on the path of `key`
+fn sibling_prefix(key: &U256, depth: u32) -> U256 {
+    let child = prefix(key, depth + 1);
+    if bit(key, depth) {
+        child - (U256::one() << depth)
+    } else {
+        child + (U256::one() << depth)
+    }
+}
+
+fn bit(key: &U256, i: u32) -> bool {
+    !((*key >> i) % U256::from(2_u32)).is_zero()
+}
+
+fn hash_leaf(leaf: &SmtLeaf) -> StdResult<U256> {
+    Poseidon::new()
+        .hash_u256(&[leaf.key, leaf.value, U256::one()])
+        .map_err(|_| StdError::generic_err("Poseidon hash failed"))
+}
+
+fn hash_node(poseidon: &Poseidon, left: &U256, right: &U256) -> StdResult<U256> {
+    poseidon
+        .hash_u256(&[*left, *right])
+        .map_err(|_| StdError::generic_err("Poseidon hash failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const TREES: SparseMerkleTreeStore = SparseMerkleTreeStore::new("levels", "nodes", "leaves");
+
+    // Root of the keys in `leaves` straight from the layout's definition
+    fn expected_root(leaves: &[SmtLeaf], depth: u32) -> U256 {
+        match leaves {
+            [] => U256::zero(),
+            [leaf] => hash_leaf(leaf).unwrap(),
+            _ => {
+                let (right, left): (Vec<SmtLeaf>, Vec<SmtLeaf>) = leaves
+                    .iter()
+                    .cloned()
+                    .partition(|leaf| bit(&leaf.key, depth));
+                hash_node(
+                    &Poseidon::new(),
+                    &expected_root(&left, depth + 1),
+                    &expected_root(&right, depth + 1),
+                )
+                .unwrap()
+            }
+        }
+    }
+
+    fn leaf(key: u32, value: u32) -> SmtLeaf {
+        SmtLeaf {
+            key: U256::from(key),
+            value: U256::from(value),
+        }
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree_proofs() {
+        let mut storage = MockStorage::new();
+        TREES.init(&mut storage, 0, 8).unwrap();
+        assert_eq!(TREES.get_root(&storage, 0).unwrap(), U256::zero());
+
+        // 1337 and 1353 share their lowest 4 bits, so they sit 5 deep
+        let leaves = vec![leaf(1337, 42), leaf(1353, 1), leaf(6, 1)];
+        for leaf in leaves.iter() {
+            TREES
+                .insert(&mut storage, 0, &leaf.key, &leaf.value)
+                .unwrap();
+        }
+        assert_eq!(
+            TREES.get_root(&storage, 0).unwrap(),
+            expected_root(&leaves, 0)
+        );
+
+        let (key, value) = (leaves[0].key, leaves[0].value);
+        assert_eq!(TREES.get(&storage, 0, &key).unwrap(), Some(value));
+        let proof = TREES.prove(&storage, 0, &key).unwrap();
+        assert_eq!(proof.siblings.len(), 5);
+        assert!(verify_membership(&proof, &key, &value));
+        assert!(!verify_membership(&proof, &key, &U256::from(43_u32)));
+        assert!(!verify_non_membership(&proof, &key));
+
+        // Path ending at an empty node
+        let absent = U256::from(3_u32);
+        let proof = TREES.prove(&storage, 0, &absent).unwrap();
+        assert_eq!(proof.leaf, None);
+        assert!(verify_non_membership(&proof, &absent));
+        assert!(!verify_membership(&proof, &absent, &U256::zero()));
+
+        // Path ending at the leaf of another key
+        let absent = U256::from(2_u32);
+        let proof = TREES.prove(&storage, 0, &absent).unwrap();
+        assert_eq!(proof.leaf, Some(leaves[2].clone()));
+        assert!(verify_non_membership(&proof, &absent));
+        assert!(!verify_non_membership(&proof, &leaves[2].key));
+
+        // Keys sharing all the bits a leaf depth can tell apart
+        assert!(TREES
+            .insert(&mut storage, 0, &(key + U256::from(128_u32)), &value)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree_update_and_delete() {
+        let mut storage = MockStorage::new();
+        TREES.init(&mut storage, 0, 254).unwrap();
+        TREES.init(&mut storage, 1, 254).unwrap();
+
+        // Keys sharing low bits in several ways, inserted in two orders
+        let mut leaves = vec![
+            leaf(7, 1),
+            leaf(1337, 2),
+            leaf(1353, 3),
+            leaf((1 << 20) + 7, 4),
+            leaf(8, 5),
+        ];
+        for leaf in leaves.iter() {
+            TREES
+                .insert(&mut storage, 0, &leaf.key, &leaf.value)
+                .unwrap();
+        }
+        for leaf in leaves.iter().rev() {
+            TREES
+                .insert(&mut storage, 1, &leaf.key, &leaf.value)
+                .unwrap();
+        }
+        let root = TREES.get_root(&storage, 0).unwrap();
+        assert_eq!(root, expected_root(&leaves, 0));
+        assert_eq!(TREES.get_root(&storage, 1).unwrap(), root);
+
+        // Updating a value is the same as inserting it directly
+        TREES
+            .insert(&mut storage, 0, &leaves[1].key, &U256::from(9_u32))
+            .unwrap();
+        leaves[1].value = U256::from(9_u32);
+        assert_eq!(
+            TREES.get_root(&storage, 0).unwrap(),
+            expected_root(&leaves, 0)
+        );
+
+        // Deleting moves lone leaves back up, so the tree is the same as never having
+        // had the key
+        while let Some(deleted) = leaves.pop() {
+            TREES.delete(&mut storage, 0, &deleted.key).unwrap();
+            assert_eq!(
+                TREES.get_root(&storage, 0).unwrap(),
+                expected_root(&leaves, 0)
+            );
+            assert_eq!(TREES.get(&storage, 0, &deleted.key).unwrap(), None);
+            assert!(TREES.delete(&mut storage, 0, &deleted.key).is_err());
+            for leaf in leaves.iter() {
+                let proof = TREES.prove(&storage, 0, &leaf.key).unwrap();
+                assert!(verify_membership(&proof, &leaf.key, &leaf.value));
+            }
+            let proof = TREES.prove(&storage, 0, &deleted.key).unwrap();
+            assert!(verify_non_membership(&proof, &deleted.key));
+        }
+        assert_eq!(TREES.get_root(&storage, 0).unwrap(), U256::zero());
+        assert_eq!(
+            TREES
+                .nodes
+                .sub_prefix(0)
+                .keys_raw(&storage, None, None, cosmwasm_std::Order::Ascending)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree_checker_inputs() {
+        // The inputs `circuits/lib/test/test_sparse_merkle_tree.sh` runs through the
+        // circuit, written by `generate-data`. They must check out natively the same way.
+        let inputs = [
+            (
+                "inclusion",
+                include_str!("../../../generate-data/outputs/smt/inclusion.json"),
+            ),
+            (
+                "inclusion_pushed_down",
+                include_str!("../../../generate-data/outputs/smt/inclusion_pushed_down.json"),
+            ),
+            (
+                "exclusion_empty",
+                include_str!("../../../generate-data/outputs/smt/exclusion_empty.json"),
+            ),
+            (
+                "exclusion_occupied",
+                include_str!("../../../generate-data/outputs/smt/exclusion_occupied.json"),
+            ),
+            (
+                "invalid_inclusion_wrong_value",
+                include_str!(
+                    "../../../generate-data/outputs/smt/invalid_inclusion_wrong_value.json"
+                ),
+            ),
+            (
+                "invalid_exclusion_of_member",
+                include_str!("../../../generate-data/outputs/smt/invalid_exclusion_of_member.json"),
+            ),
+        ];
+
+        for (name, json) in inputs.iter() {
+            let inputs: SmtCheckerInputs = serde_json::from_str(json).unwrap();
+            let mut siblings = inputs.siblings.clone();
+            while siblings.last().is_some_and(|sibling| sibling.is_zero()) {
+                siblings.pop();
+            }
+            let proof = SmtProof {
+                root: inputs.root,
+                siblings,
+                leaf: if inputs.isOld0.is_zero() {
+                    Some(SmtLeaf {
+                        key: if inputs.fnc.is_zero() {
+                            inputs.key
+                        } else {
+                            inputs.oldKey
+                        },
+                        value: if inputs.fnc.is_zero() {
+                            inputs.value
+                        } else {
+                            inputs.oldValue
+                        },
+                    })
+                } else {
+                    None
+                },
+            };
+            let valid = if inputs.fnc.is_zero() {
+                verify_membership(&proof, &inputs.key, &inputs.value)
+            } else {
+                verify_non_membership(&proof, &inputs.key)
+            };
+            assert_eq!(valid, !name.starts_with("invalid_"), "{}", name);
+        }
+    }
+}
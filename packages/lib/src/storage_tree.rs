@@ -0,0 +1,328 @@
+use cosmwasm_std::{StdError, StdResult, Storage, Uint256 as U256};
+use cw_storage_plus::Map;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+use crate::merkle_tree::{batch_root, MerkleTreeWithHistory};
+use crate::tree_hasher::{PoseidonHasher, TreeHasher};
+use crate::zeros::ZEROS;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TreeMeta {
+    pub levels: u32,
+    pub next_index: u32,
+    pub current_root_index: u32,
+    pub root_history_size: u32,
+}
+
+/// Merkle trees with root history kept in contract storage. Filled subtrees and
+/// roots each live under their own key, so an insert reads and writes only the
+/// subtrees on its path and a single root slot. Several trees can share one
+/// store, told apart by `tree_id`, and hash their nodes with `H`.
+pub struct MerkleTreeStore<'a, H: TreeHasher = PoseidonHasher> {
+    meta: Map<'a, u32, TreeMeta>,
+    filled_subtrees: Map<'a, (u32, u32), U256>,
+    roots: Map<'a, (u32, u32), U256>,
+    // Root history slot each root was written to, so lookups don't scan the history
+    root_slots: Map<'a, (u32, String), u32>,
+    hasher: PhantomData<H>,
+}
+
+impl<'a, H: TreeHasher> MerkleTreeStore<'a, H> {
+    pub const fn new(
+        meta_namespace: &'a str,
+        filled_subtrees_namespace: &'a str,
+        roots_namespace: &'a str,
+        root_slots_namespace: &'a str,
+    ) -> Self {
+        Self {
+            meta: Map::new(meta_namespace),
+            filled_subtrees: Map::new(filled_subtrees_namespace),
+            roots: Map::new(roots_namespace),
+            root_slots: Map::new(root_slots_namespace),
+            hasher: PhantomData,
+        }
+    }
+
+    /// Creates an empty tree. Missing filled subtrees are never read, as the
+    /// left sibling of a node is always written before its right sibling.
+    pub fn init(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        levels: u32,
+        root_history_size: u32,
+    ) -> StdResult<()> {
+        if levels == 0 || levels >= ZEROS.len() as u32 {
+            return Err(StdError::generic_err("Invalid tree levels"));
+        }
+        if root_history_size == 0 {
+            return Err(StdError::generic_err(
+                "Root history size should be greater than zero",
+            ));
+        }
+        if self.meta.has(storage, tree_id) {
+            return Err(StdError::generic_err("Tree already exists"));
+        }
+
+        self.meta.save(
+            storage,
+            tree_id,
+            &TreeMeta {
+                levels,
+                next_index: 0,
+                current_root_index: 0,
+                root_history_size,
+            },
+        )?;
+        self.save_root(storage, tree_id, 0, H::zeros(levels as usize)[levels as usize])
+    }
+
+    /// Copies a tree kept as a single value into the store
+    pub fn import(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        tree: &MerkleTreeWithHistory<H>,
+    ) -> StdResult<()> {
+        self.meta.save(
+            storage,
+            tree_id,
+            &TreeMeta {
+                levels: tree.levels,
+                next_index: tree.next_index,
+                current_root_index: tree.current_root_index,
+                root_history_size: tree.root_history_size(),
+            },
+        )?;
+        for (level, subtree) in tree.filled_subtrees.iter().enumerate() {
+            self.filled_subtrees
+                .save(storage, (tree_id, level as u32), subtree)?;
+        }
+        for (slot, root) in tree.roots.iter().enumerate() {
+            if !root.is_zero() {
+                self.save_root(storage, tree_id, slot as u32, *root)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn meta(&self, storage: &dyn Storage, tree_id: u32) -> StdResult<TreeMeta> {
+        self.meta.load(storage, tree_id)
+    }
+
+    /// Inserts `leaf` and returns its index
+    pub fn insert(&self, storage: &mut dyn Storage, tree_id: u32, leaf: &U256) -> StdResult<u32> {
+        let mut meta = self.meta.load(storage, tree_id)?;
+        let leaf_index = meta.next_index;
+        if leaf_index == 2_u32.saturating_pow(meta.levels) {
+            return Err(StdError::generic_err(
+                "Merkle tree is full. No more leaves can be added",
+            ));
+        }
+
+        let zeros = H::zeros(meta.levels as usize);
+        let mut idx = leaf_index;
+        let mut current_level_hash = *leaf;
+        for level in 0..meta.levels {
+            let (left, right) = if idx % 2 == 0 {
+                self.filled_subtrees
+                    .save(storage, (tree_id, level), &current_level_hash)?;
+                (current_level_hash, zeros[level as usize])
+            } else {
+                let left = self.filled_subtrees.load(storage, (tree_id, level))?;
+                (left, current_level_hash)
+            };
+            current_level_hash = H::hash_left_right(&left, &right)
+                .ok_or_else(|| StdError::generic_err("Leaf is not a valid hash input"))?;
+            idx /= 2;
+        }
+
+        meta.next_index += 1;
+        meta.current_root_index = (meta.current_root_index + 1) % meta.root_history_size;
+        self.meta.save(storage, tree_id, &meta)?;
+        self.save_root(storage, tree_id, meta.current_root_index, current_level_hash)?;
+
+        Ok(leaf_index)
+    }
+
+    /// Inserts `leaves` in order and returns the index of the first one, recording
+    /// only the root after the last leaf
+    pub fn insert_batch(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        leaves: &[U256],
+    ) -> StdResult<u32> {
+        let mut meta = self.meta.load(storage, tree_id)?;
+        let start = meta.next_index;
+        if leaves.is_empty() {
+            return Err(StdError::generic_err("No leaves to insert"));
+        }
+        if (start as u64 + leaves.len() as u64) > 2_u64.pow(meta.levels) {
+            return Err(StdError::generic_err(
+                "Merkle tree is full. No more leaves can be added",
+            ));
+        }
+
+        // Subtrees that were never filled are never read, so zeros stand in for them
+        let zeros = H::zeros(meta.levels as usize);
+        let mut filled_subtrees = (0..meta.levels)
+            .map(|level| {
+                Ok(self
+                    .filled_subtrees
+                    .may_load(storage, (tree_id, level))?
+                    .unwrap_or(zeros[level as usize]))
+            })
+            .collect::<StdResult<Vec<U256>>>()?;
+        let previous_subtrees = filled_subtrees.clone();
+        let root = batch_root::<H>(
+            &zeros[..meta.levels as usize],
+            &mut filled_subtrees,
+            start,
+            leaves,
+        )
+        .ok_or_else(|| StdError::generic_err("Leaf is not a valid hash input"))?;
+        for (level, subtree) in filled_subtrees.iter().enumerate() {
+            if *subtree != previous_subtrees[level] {
+                self.filled_subtrees
+                    .save(storage, (tree_id, level as u32), subtree)?;
+            }
+        }
+
+        meta.next_index += leaves.len() as u32;
+        meta.current_root_index = (meta.current_root_index + 1) % meta.root_history_size;
+        self.meta.save(storage, tree_id, &meta)?;
+        self.save_root(storage, tree_id, meta.current_root_index, root)?;
+
+        Ok(start)
+    }
+
+    pub fn is_known_root(&self, storage: &dyn Storage, tree_id: u32, root: &U256) -> StdResult<bool> {
+        if root.is_zero() {
+            return Ok(false);
+        }
+        match self.root_slots.may_load(storage, (tree_id, root.to_string()))? {
+            Some(slot) => Ok(self.roots.may_load(storage, (tree_id, slot))? == Some(*root)),
+            None => Ok(false),
+        }
+    }
+
+    pub fn get_last_root(&self, storage: &dyn Storage, tree_id: u32) -> StdResult<U256> {
+        let meta = self.meta.load(storage, tree_id)?;
+        self.roots.load(storage, (tree_id, meta.current_root_index))
+    }
+
+    fn save_root(
+        &self,
+        storage: &mut dyn Storage,
+        tree_id: u32,
+        slot: u32,
+        root: U256,
+    ) -> StdResult<()> {
+        // Forget the root this slot held before
+        if let Some(old_root) = self.roots.may_load(storage, (tree_id, slot))? {
+            self.root_slots.remove(storage, (tree_id, old_root.to_string()));
+        }
+        self.roots.save(storage, (tree_id, slot), &root)?;
+        self.root_slots.save(storage, (tree_id, root.to_string()), &slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bignum;
+    use crate::tree_hasher::SumHasher;
+    use cosmwasm_std::testing::MockStorage;
+
+    const TREES: MerkleTreeStore = MerkleTreeStore::new("meta", "filled", "roots", "root_slots");
+
+    #[test]
+    fn test_storage_tree_matches_merkle_tree() {
+        let mut storage = MockStorage::new();
+        TREES.init(&mut storage, 0, 20, 3).unwrap();
+        assert_eq!(
+            TREES.get_last_root(&storage, 0).unwrap(),
+            bignum!("19476726467694243150694636071195943429153087843379888650723427850220480216251")
+        );
+
+        let mut mt = MerkleTreeWithHistory::with_root_history_size(20, 3);
+        for leaf in [42_u32, 7, 1337, 9] {
+            let index = TREES.insert(&mut storage, 0, &U256::from(leaf)).unwrap();
+            assert_eq!(Some(index), mt.insert(&U256::from(leaf)));
+            assert_eq!(TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+        }
+        for root in mt.roots.iter() {
+            assert!(TREES.is_known_root(&storage, 0, root).unwrap());
+        }
+
+        // Roots fall out of the history like in `MerkleTreeWithHistory`
+        let mut first = MerkleTreeWithHistory::new(20);
+        first.insert(&U256::from(42_u32));
+        assert!(!TREES.is_known_root(&storage, 0, &first.get_last_root()).unwrap());
+
+        // Trees are independent
+        TREES.init(&mut storage, 1, 20, 3).unwrap();
+        assert!(!TREES.is_known_root(&storage, 1, &mt.get_last_root()).unwrap());
+    }
+
+    #[test]
+    fn test_storage_tree_insert_batch() {
+        let mut storage = MockStorage::new();
+        TREES.init(&mut storage, 0, 20, 3).unwrap();
+        let mut mt = MerkleTreeWithHistory::with_root_history_size(20, 3);
+
+        let leaves: Vec<U256> = (1..=5_u32).map(U256::from).collect();
+        assert_eq!(TREES.insert_batch(&mut storage, 0, &leaves[..3]).unwrap(), 0);
+        assert_eq!(TREES.insert_batch(&mut storage, 0, &leaves[3..]).unwrap(), 3);
+        mt.insert_batch(&leaves[..3]);
+        mt.insert_batch(&leaves[3..]);
+        assert_eq!(TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+        assert_eq!(TREES.meta(&storage, 0).unwrap().next_index, 5);
+
+        // Single inserts pick up the subtrees the batch filled
+        TREES.insert(&mut storage, 0, &U256::from(42_u32)).unwrap();
+        mt.insert(&U256::from(42_u32));
+        assert_eq!(TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+        assert!(TREES.insert_batch(&mut storage, 0, &[]).is_err());
+        assert!(TREES.insert_batch(&mut storage, 0, &[U256::MAX]).is_err());
+        assert!(TREES.insert(&mut storage, 0, &U256::MAX).is_err());
+    }
+
+    #[test]
+    fn test_storage_tree_custom_hasher() {
+        const SUM_TREES: MerkleTreeStore<SumHasher> =
+            MerkleTreeStore::new("meta", "filled", "roots", "root_slots");
+
+        let mut storage = MockStorage::new();
+        SUM_TREES.init(&mut storage, 0, 4, 3).unwrap();
+        let mut mt = MerkleTreeWithHistory::<SumHasher>::with_hasher(4, 3);
+        assert_eq!(SUM_TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+
+        let leaves: Vec<U256> = (1..=5_u32).map(U256::from).collect();
+        SUM_TREES.insert(&mut storage, 0, &leaves[0]).unwrap();
+        SUM_TREES.insert_batch(&mut storage, 0, &leaves[1..]).unwrap();
+        mt.insert(&leaves[0]);
+        mt.insert_batch(&leaves[1..]);
+        assert_eq!(SUM_TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+        assert_ne!(mt.get_last_root(), MerkleTreeWithHistory::new(4).get_last_root());
+    }
+
+    #[test]
+    fn test_storage_tree_import() {
+        let mut mt = MerkleTreeWithHistory::new(20);
+        mt.insert(&U256::from(42_u32));
+        mt.insert(&U256::from(7_u32));
+
+        let mut storage = MockStorage::new();
+        TREES.import(&mut storage, 0, &mt).unwrap();
+        assert_eq!(TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+
+        // Inserting after the import continues the same tree
+        TREES.insert(&mut storage, 0, &U256::from(1337_u32)).unwrap();
+        mt.insert(&U256::from(1337_u32));
+        assert_eq!(TREES.get_last_root(&storage, 0).unwrap(), mt.get_last_root());
+        assert_eq!(TREES.meta(&storage, 0).unwrap().next_index, 3);
+    }
+}
@@ -0,0 +1,108 @@
+use cosmwasm_std::Uint256 as U256;
+
+use crate::poseidon::Poseidon;
+use crate::zeros::ZEROS;
+
+/// Hash of two sibling nodes, used by `MerkleTreeWithHistory` and `MerkleTreeStore`
+/// for every level
+pub trait TreeHasher {
+    /// Returns `None` if the nodes aren't valid inputs of the hash
+    fn hash_left_right(left: &U256, right: &U256) -> Option<U256>;
+
+    /// Roots of empty subtrees of heights 0 to `levels`, starting from the zero leaf
+    fn zeros(levels: usize) -> Vec<U256> {
+        let mut zeros = vec![ZEROS[0]];
+        for i in 0..levels {
+            let zero = Self::hash_left_right(&zeros[i], &zeros[i])
+                .expect("empty subtrees hash like any other node");
+            zeros.push(zero);
+        }
+        zeros
+    }
+}
+
+/// Poseidon over BN254, as in `circuits/lib/merkleTree.circom`
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct PoseidonHasher;
+
+impl TreeHasher for PoseidonHasher {
+    // Nodes at or above the field modulus are rejected rather than reduced
+    fn hash_left_right(left: &U256, right: &U256) -> Option<U256> {
+        let poseidon = Poseidon::new();
+        poseidon.hash_u256(&[*left, *right]).ok()
+    }
+
+    // Precomputed, as hashing them on every insert would double its cost
+    fn zeros(levels: usize) -> Vec<U256> {
+        ZEROS[..=levels].to_vec()
+    }
+}
+
+/// Cheap stand-in for Poseidon, to exercise the tree logic on bigger trees
+#[cfg(test)]
+#[derive(Default, Clone, Debug, PartialEq)]
+pub(crate) struct SumHasher;
+
+#[cfg(test)]
+impl TreeHasher for SumHasher {
+    fn hash_left_right(left: &U256, right: &U256) -> Option<U256> {
+        Some(left.wrapping_mul(U256::from(3_u32)).wrapping_add(*right))
+    }
+}
+
+/// SHA-256 of the big-endian left and right nodes
+#[cfg(feature = "extra-hashers")]
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Sha256Hasher;
+
+#[cfg(feature = "extra-hashers")]
+impl TreeHasher for Sha256Hasher {
+    fn hash_left_right(left: &U256, right: &U256) -> Option<U256> {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(left.to_be_bytes());
+        hasher.update(right.to_be_bytes());
+        Some(U256::from_be_bytes(hasher.finalize().into()))
+    }
+}
+
+/// Keccak-256 of the big-endian left and right nodes, like `keccak256(abi.encodePacked(left, right))`
+#[cfg(feature = "extra-hashers")]
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Keccak256Hasher;
+
+#[cfg(feature = "extra-hashers")]
+impl TreeHasher for Keccak256Hasher {
+    fn hash_left_right(left: &U256, right: &U256) -> Option<U256> {
+        use sha3::{Digest, Keccak256};
+
+        let mut hasher = Keccak256::new();
+        hasher.update(left.to_be_bytes());
+        hasher.update(right.to_be_bytes());
+        Some(U256::from_be_bytes(hasher.finalize().into()))
+    }
+}
+
+#[cfg(all(test, feature = "extra-hashers"))]
+mod tests {
+    use super::*;
+    use crate::bignum;
+
+    #[test]
+    fn test_extra_hashers() {
+        // sha256 and keccak256 of 64 zero bytes
+        assert_eq!(
+            Sha256Hasher::hash_left_right(&U256::zero(), &U256::zero()).unwrap(),
+            bignum!(
+                "111109925611824843164212799849330761292948257037696933205019304127221294824267"
+            )
+        );
+        assert_eq!(
+            Keccak256Hasher::hash_left_right(&U256::zero(), &U256::zero()).unwrap(),
+            bignum!(
+                "78338746147236970124700731725183845421594913511827187288591969170390706184117"
+            )
+        );
+    }
+}
@@ -23,13 +23,33 @@ impl Verifier {
         Self { vk_json }
     }
 
+    /// Proofs fail to verify, rather than panic, when the key can't be parsed or
+    /// expects a different number of public inputs
     pub fn verify_proof(self, proof: Proof<Bn254>, inputs: &[Fr]) -> bool {
-        let vk_json: VerifyingKeyJson = serde_json::from_str(&self.vk_json).unwrap();
+        let vk_json: VerifyingKeyJson = match serde_json::from_str(&self.vk_json) {
+            Ok(vk_json) => vk_json,
+            Err(_) => return false,
+        };
 
         let vk = vk_json.to_verifying_key();
         let pvk = prepare_verifying_key(&vk);
 
-        verify_proof(&pvk, &proof, inputs).unwrap()
+        verify_proof(&pvk, &proof, inputs).unwrap_or(false)
+    }
+
+    /// Verifies several proofs against this key, parsing and preparing the key once
+    pub fn verify_proofs(self, proofs: Vec<(Proof<Bn254>, Vec<Fr>)>) -> bool {
+        let vk_json: VerifyingKeyJson = match serde_json::from_str(&self.vk_json) {
+            Ok(vk_json) => vk_json,
+            Err(_) => return false,
+        };
+
+        let vk = vk_json.to_verifying_key();
+        let pvk = prepare_verifying_key(&vk);
+
+        proofs
+            .iter()
+            .all(|(proof, inputs)| verify_proof(&pvk, proof, inputs).unwrap_or(false))
     }
 }
 
@@ -279,9 +299,21 @@ mod tests {
             "2880600617345714039494384748645461738150340256226005947162982605579534386469"
           ]"#.to_string());
 
-        let res = v.verify_proof(proof, &public_signals.get());
+        let res = v
+            .clone()
+            .verify_proof(proof.clone(), &public_signals.clone().get());
 
         println!("res: {}", res);
         assert!(res);
+
+        // A key for a different number of public inputs rejects the proof
+        let mut extra_signals = public_signals.clone().get();
+        extra_signals.push(Fr::from(0u64));
+        assert!(!v.clone().verify_proof(proof.clone(), &extra_signals));
+        assert!(!v.verify_proofs(vec![(proof.clone(), extra_signals)]));
+
+        // So does a key that can't be parsed
+        let res = Verifier::from_vk("{}".to_string()).verify_proof(proof, &public_signals.get());
+        assert!(!res);
     }
 }
@@ -0,0 +1,246 @@
+use cosmwasm_std::Uint256 as U256;
+
+/// Root of an empty subtree of each height, starting from `ZERO_VALUE` leaves.
+/// `ZEROS[levels]` is the root of an empty tree with `levels` levels.
+pub const ZEROS: [U256; 32] = [
+    // 21663839004416932945382355908790599225266501822907911457504978515578255421292
+    U256::from_be_bytes([
+        0x2f, 0xe5, 0x4c, 0x60, 0xd3, 0xac, 0xab, 0xf3,
+        0x34, 0x3a, 0x35, 0xb6, 0xeb, 0xa1, 0x5d, 0xb4,
+        0x82, 0x1b, 0x34, 0x0f, 0x76, 0xe7, 0x41, 0xe2,
+        0x24, 0x96, 0x85, 0xed, 0x48, 0x99, 0xaf, 0x6c,
+    ]),
+    // 8995896153219992062710898675021891003404871425075198597897889079729967997688
+    U256::from_be_bytes([
+        0x13, 0xe3, 0x7f, 0x2d, 0x6c, 0xb8, 0x6c, 0x78,
+        0xcc, 0xc1, 0x78, 0x86, 0x07, 0xc2, 0xb1, 0x99,
+        0x78, 0x8c, 0x6b, 0xb0, 0xa6, 0x15, 0xa2, 0x1f,
+        0x2e, 0x7a, 0x8e, 0x88, 0x38, 0x42, 0x22, 0xf8,
+    ]),
+    // 15126246733515326086631621937388047923581111613947275249184377560170833782629
+    U256::from_be_bytes([
+        0x21, 0x71, 0x26, 0xfa, 0x35, 0x2c, 0x32, 0x68,
+        0x96, 0xe8, 0xc2, 0x80, 0x3e, 0xec, 0x8f, 0xd6,
+        0x3a, 0xd5, 0x0c, 0xf6, 0x5e, 0xdf, 0xef, 0x27,
+        0xa4, 0x1a, 0x9e, 0x32, 0xdc, 0x62, 0x27, 0x65,
+    ]),
+    // 6404200169958188928270149728908101781856690902670925316782889389790091378414
+    U256::from_be_bytes([
+        0x0e, 0x28, 0xa6, 0x1a, 0x9b, 0x3e, 0x91, 0x00,
+        0x7d, 0x5a, 0x9e, 0x3a, 0xda, 0x18, 0xe1, 0xb2,
+        0x4d, 0x6d, 0x23, 0x0c, 0x61, 0x83, 0x88, 0xee,
+        0x5d, 0xf3, 0x4c, 0xac, 0xd7, 0x39, 0x7e, 0xee,
+    ]),
+    // 17903822129909817717122288064678017104411031693253675943446999432073303897479
+    U256::from_be_bytes([
+        0x27, 0x95, 0x34, 0x47, 0xa6, 0x97, 0x98, 0x39,
+        0x53, 0x6b, 0xad, 0xc5, 0x42, 0x5e, 0xd1, 0x5f,
+        0xad, 0xb0, 0xe2, 0x92, 0xe9, 0xbc, 0x36, 0xf9,
+        0x2f, 0x0a, 0xa5, 0xcf, 0xa5, 0x01, 0x35, 0x87,
+    ]),
+    // 11423673436710698439362231088473903829893023095386581732682931796661338615804
+    U256::from_be_bytes([
+        0x19, 0x41, 0x91, 0xed, 0xbf, 0xb9, 0x1d, 0x10,
+        0xf6, 0xa7, 0xaf, 0xd3, 0x15, 0xf3, 0x30, 0x95,
+        0x41, 0x0c, 0x78, 0x01, 0xc4, 0x71, 0x75, 0xc2,
+        0xdf, 0x6d, 0xc2, 0xcc, 0xe0, 0xe3, 0xaf, 0xfc,
+    ]),
+    // 10494842461667482273766668782207799332467432901404302674544629280016211342367
+    U256::from_be_bytes([
+        0x17, 0x33, 0xde, 0xce, 0x17, 0xd7, 0x11, 0x90,
+        0x51, 0x6d, 0xba, 0xf1, 0x92, 0x79, 0x36, 0xfa,
+        0x64, 0x3d, 0xc7, 0x07, 0x9f, 0xc0, 0xcc, 0x73,
+        0x1d, 0xe9, 0xd6, 0x84, 0x5a, 0x47, 0x74, 0x1f,
+    ]),
+    // 17400501067905286947724900644309270241576392716005448085614420258732805558809
+    U256::from_be_bytes([
+        0x26, 0x78, 0x55, 0xa7, 0xdc, 0x75, 0xdb, 0x39,
+        0xd8, 0x1d, 0x17, 0xf9, 0x5d, 0x0a, 0x7a, 0xa5,
+        0x72, 0xbf, 0x5a, 0xe1, 0x9f, 0x4d, 0xb0, 0xe8,
+        0x42, 0x21, 0xd2, 0xb2, 0xef, 0x99, 0x92, 0x19,
+    ]),
+    // 7924095784194248701091699324325620647610183513781643345297447650838438175245
+    U256::from_be_bytes([
+        0x11, 0x84, 0xe1, 0x18, 0x36, 0xb4, 0xc3, 0x6a,
+        0xd8, 0x23, 0x8a, 0x34, 0x0e, 0xcc, 0x09, 0x85,
+        0xee, 0xba, 0x66, 0x53, 0x27, 0xe3, 0x3e, 0x9b,
+        0x0e, 0x36, 0x41, 0x02, 0x7c, 0x27, 0x62, 0x0d,
+    ]),
+    // 3170907381568164996048434627595073437765146540390351066869729445199396390350
+    U256::from_be_bytes([
+        0x07, 0x02, 0xab, 0x83, 0xa1, 0x35, 0xd7, 0xf5,
+        0x53, 0x50, 0xab, 0x1b, 0xfa, 0xa9, 0x0b, 0xab,
+        0xd8, 0xfc, 0x1d, 0x2b, 0x3e, 0x6a, 0x72, 0x15,
+        0x38, 0x1a, 0x7b, 0x22, 0x13, 0xd6, 0xc5, 0xce,
+    ]),
+    // 21224698076141654110749227566074000819685780865045032659353546489395159395031
+    U256::from_be_bytes([
+        0x2e, 0xec, 0xc0, 0xde, 0x81, 0x4c, 0xfd, 0x8c,
+        0x57, 0xce, 0x88, 0x2b, 0xab, 0xb2, 0xe3, 0x0d,
+        0x1d, 0xa5, 0x66, 0x21, 0xae, 0xf7, 0xa4, 0x7f,
+        0x32, 0x91, 0xcf, 0xfe, 0xae, 0xc2, 0x6a, 0xd7,
+    ]),
+    // 18113275293366123216771546175954550524914431153457717566389477633419482708807
+    U256::from_be_bytes([
+        0x28, 0x0b, 0xc0, 0x21, 0x45, 0xc1, 0x55, 0xd5,
+        0x83, 0x35, 0x85, 0xb6, 0xc7, 0xb0, 0x85, 0x01,
+        0x05, 0x51, 0x57, 0xdd, 0x30, 0xce, 0x00, 0x53,
+        0x19, 0x62, 0x1d, 0xc4, 0x62, 0xd3, 0x3b, 0x47,
+    ]),
+    // 1952712013602708178570747052202251655221844679392349715649271315658568301659
+    U256::from_be_bytes([
+        0x04, 0x51, 0x32, 0x22, 0x1d, 0x1f, 0xa0, 0xa7,
+        0xf4, 0xae, 0xd8, 0xac, 0xd2, 0xcb, 0xec, 0x1e,
+        0x21, 0x89, 0xb7, 0x73, 0x2c, 0xcb, 0x2e, 0xc2,
+        0x72, 0xb9, 0xc6, 0x0f, 0x0d, 0x5a, 0xfc, 0x5b,
+    ]),
+    // 18071586466641072671725723167170872238457150900980957071031663421538421560166
+    U256::from_be_bytes([
+        0x27, 0xf4, 0x27, 0xcc, 0xbf, 0x58, 0xa4, 0x4b,
+        0x12, 0x70, 0xab, 0xbe, 0x4e, 0xda, 0x6b, 0xa5,
+        0x3b, 0xd6, 0xac, 0x4d, 0x88, 0xcf, 0x1e, 0x00,
+        0xa1, 0x3c, 0x43, 0x71, 0xce, 0x71, 0xd3, 0x66,
+    ]),
+    // 9993139859464142980356243228522899168680191731482953959604385644693217291503
+    U256::from_be_bytes([
+        0x16, 0x17, 0xea, 0xae, 0x50, 0x64, 0xf2, 0x6e,
+        0x8f, 0x8a, 0x64, 0x93, 0xae, 0x92, 0xbf, 0xde,
+        0xd7, 0xfd, 0xe7, 0x1b, 0x65, 0xdf, 0x1c, 0xa6,
+        0xd5, 0xdc, 0xec, 0x0d, 0xf7, 0x0b, 0x2c, 0xef,
+    ]),
+    // 14825089209834329031146290681677780462512538924857394026404638992248153156554
+    U256::from_be_bytes([
+        0x20, 0xc6, 0xb4, 0x00, 0xd0, 0xea, 0x1b, 0x15,
+        0x43, 0x57, 0x03, 0xc3, 0x1c, 0x31, 0xee, 0x63,
+        0xad, 0x7b, 0xa5, 0xc8, 0xda, 0x66, 0xce, 0xc2,
+        0x79, 0x6f, 0xea, 0xce, 0xa5, 0x75, 0xab, 0xca,
+    ]),
+    // 4227387664466178643628175945231814400524887119677268757709033164980107894508
+    U256::from_be_bytes([
+        0x09, 0x58, 0x9d, 0xdb, 0x43, 0x87, 0x23, 0xf5,
+        0x3a, 0x8e, 0x57, 0xbd, 0xad, 0xa7, 0xc5, 0xf8,
+        0xed, 0x67, 0xe8, 0xfe, 0xce, 0x38, 0x89, 0xa7,
+        0x36, 0x18, 0x73, 0x29, 0x65, 0x64, 0x5e, 0xec,
+    ]),
+    // 177945332589823419436506514313470826662740485666603469953512016396504401819
+    U256::from_be_bytes([
+        0x00, 0x64, 0xb6, 0xa7, 0x38, 0xa5, 0xff, 0x53,
+        0x7d, 0xb7, 0xb2, 0x20, 0xf3, 0x39, 0x4f, 0x0e,
+        0xcb, 0xd3, 0x5b, 0xfd, 0x35, 0x5c, 0x54, 0x25,
+        0xdc, 0x11, 0x66, 0xbf, 0x32, 0x36, 0x07, 0x9b,
+    ]),
+    // 4236715569920417171293504597566056255435509785944924295068274306682611080863
+    U256::from_be_bytes([
+        0x09, 0x5d, 0xe5, 0x62, 0x81, 0xb1, 0xd5, 0x05,
+        0x5e, 0x89, 0x7c, 0x35, 0x74, 0xff, 0x79, 0x0d,
+        0x5e, 0xe8, 0x1d, 0xbc, 0x5d, 0xf7, 0x84, 0xad,
+        0x2d, 0x67, 0x79, 0x5e, 0x55, 0x7c, 0x9e, 0x9f,
+    ]),
+    // 8055374341341620501424923482910636721817757020788836089492629714380498049891
+    U256::from_be_bytes([
+        0x11, 0xcf, 0x2e, 0x28, 0x87, 0xaa, 0x21, 0x96,
+        0x3a, 0x6e, 0xc1, 0x42, 0x89, 0x18, 0x3e, 0xfe,
+        0x4d, 0x4c, 0x60, 0xf1, 0x4e, 0xcd, 0x3d, 0x6f,
+        0xe0, 0xbe, 0xeb, 0xdf, 0x85, 0x5a, 0x9b, 0x63,
+    ]),
+    // 19476726467694243150694636071195943429153087843379888650723427850220480216251
+    U256::from_be_bytes([
+        0x2b, 0x0f, 0x6f, 0xc0, 0x17, 0x9f, 0xa6, 0x5b,
+        0x6f, 0x73, 0x62, 0x7c, 0x0e, 0x1e, 0x84, 0xc7,
+        0x37, 0x4d, 0x2e, 0xae, 0xc4, 0x4c, 0x9a, 0x48,
+        0xf2, 0x57, 0x13, 0x93, 0xea, 0x77, 0xbc, 0xbb,
+    ]),
+    // 10399152597354305597912907824309239409817888262144718512380299069916269589113
+    U256::from_be_bytes([
+        0x16, 0xfd, 0xb6, 0x37, 0xc2, 0xab, 0xf9, 0xc0,
+        0xf9, 0x88, 0xdb, 0xf2, 0xfd, 0x64, 0x25, 0x8c,
+        0x46, 0xfb, 0x6a, 0x27, 0x3d, 0x53, 0x7b, 0x2c,
+        0xf1, 0x60, 0x3e, 0xa4, 0x60, 0xb1, 0x32, 0x79,
+    ]),
+    // 15258214568755945879473165452099056399152522811554450867424704782105583612159
+    U256::from_be_bytes([
+        0x21, 0xbb, 0xd7, 0xe9, 0x44, 0xf6, 0x12, 0x4d,
+        0xad, 0x4c, 0x37, 0x6d, 0xf9, 0xcc, 0x12, 0xe7,
+        0xca, 0x66, 0xe4, 0x7d, 0xff, 0x70, 0x3f, 0xf7,
+        0xce, 0xdb, 0x1a, 0x45, 0x4e, 0xdc, 0xf0, 0xff,
+    ]),
+    // 17875137458200521792878123299688785698619987766564867631132505563522438895970
+    U256::from_be_bytes([
+        0x27, 0x84, 0xf8, 0x22, 0x0b, 0x1c, 0x96, 0x3e,
+        0x46, 0x8f, 0x59, 0x0f, 0x13, 0x7b, 0xaa, 0xa1,
+        0x62, 0x5b, 0x3b, 0x92, 0xa2, 0x7a, 0xd9, 0xb6,
+        0xe8, 0x4e, 0xb0, 0xd3, 0x45, 0x4d, 0x99, 0x62,
+    ]),
+    // 10256337741884550510386441312852347693461186988936458078715216316630265625616
+    U256::from_be_bytes([
+        0x16, 0xac, 0xe1, 0xa6, 0x5b, 0x75, 0x34, 0x14,
+        0x2f, 0x8c, 0xc1, 0xaa, 0xd8, 0x10, 0xb3, 0xd6,
+        0xa7, 0xa7, 0x4c, 0xa9, 0x05, 0xd9, 0xc2, 0x75,
+        0xcb, 0x98, 0xba, 0x57, 0xe5, 0x09, 0xfc, 0x10,
+    ]),
+    // 15901668779096826777384382311437482837232013711720349479070803061728347594771
+    U256::from_be_bytes([
+        0x23, 0x28, 0x06, 0x8c, 0x6a, 0x8c, 0x24, 0x26,
+        0x51, 0x24, 0xde, 0xbd, 0x8f, 0xe1, 0x0d, 0x3f,
+        0x29, 0xf0, 0x66, 0x5e, 0xa7, 0x25, 0xa6, 0x5e,
+        0x36, 0x38, 0xf6, 0x19, 0x2a, 0x96, 0xa0, 0x13,
+    ]),
+    // 20742074412337132804130107876526602108986687824137082336286554983131679140884
+    U256::from_be_bytes([
+        0x2d, 0xdb, 0x99, 0x1b, 0xe1, 0xf0, 0x28, 0x02,
+        0x24, 0x11, 0xb4, 0xc4, 0xd2, 0xc2, 0x20, 0x43,
+        0xe5, 0xe7, 0x51, 0xc1, 0x20, 0x73, 0x6f, 0x00,
+        0xad, 0xf5, 0x4a, 0xca, 0xb1, 0xc9, 0xac, 0x14,
+    ]),
+    // 486721614616614661899683022609286155116112838076254734405175415967221587136
+    U256::from_be_bytes([
+        0x01, 0x13, 0x79, 0x84, 0x10, 0xea, 0xeb, 0x95,
+        0x05, 0x6a, 0x46, 0x4f, 0x70, 0x52, 0x1e, 0xb5,
+        0x83, 0x77, 0xc0, 0x15, 0x5f, 0x2f, 0xe5, 0x18,
+        0xa5, 0x59, 0x4d, 0x38, 0xcc, 0x20, 0x9c, 0xc0,
+    ]),
+    // 14553704920999879116288394486755121409791035465066405350421564694668688837968
+    U256::from_be_bytes([
+        0x20, 0x2d, 0x1a, 0xe6, 0x15, 0x26, 0xf0, 0xd0,
+        0xd0, 0x1e, 0xf8, 0x0f, 0xb5, 0xd4, 0x05, 0x5a,
+        0x7a, 0xf4, 0x57, 0x21, 0x02, 0x4c, 0x2c, 0x24,
+        0xcf, 0xfd, 0x6a, 0x37, 0x98, 0xf5, 0x4d, 0x50,
+    ]),
+    // 16133427046514840932414333181474318783048630149216801191847554534526344556935
+    U256::from_be_bytes([
+        0x23, 0xab, 0x32, 0x34, 0x53, 0x74, 0x81, 0x29,
+        0xf2, 0x76, 0x5f, 0x79, 0x61, 0x50, 0x22, 0xf5,
+        0xbe, 0xbd, 0x6f, 0x40, 0x96, 0xa7, 0x96, 0x30,
+        0x0a, 0xab, 0x04, 0x9a, 0x60, 0xb0, 0xf1, 0x87,
+    ]),
+    // 14059412023772412256724985181143164416212338358739056294444144007074935376544
+    U256::from_be_bytes([
+        0x1f, 0x15, 0x58, 0x5f, 0x89, 0x47, 0xe3, 0x78,
+        0xbc, 0xf8, 0xbd, 0x91, 0x87, 0x16, 0x79, 0x9d,
+        0xa9, 0x09, 0xac, 0xdb, 0x94, 0x4c, 0x57, 0x15,
+        0x0b, 0x1e, 0xb4, 0x56, 0x5f, 0xda, 0x8a, 0xa0,
+    ]),
+    // 13881045516124413800956196131444791556618225569425822624448318037713570082054
+    U256::from_be_bytes([
+        0x1e, 0xb0, 0x64, 0xb2, 0x10, 0x55, 0xac, 0x6a,
+        0x35, 0x0c, 0xf4, 0x1e, 0xb3, 0x0e, 0x4c, 0xe2,
+        0xcb, 0x19, 0x68, 0x02, 0x17, 0xdf, 0x3a, 0x24,
+        0x36, 0x17, 0xc2, 0x83, 0x81, 0x85, 0xad, 0x06,
+    ]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle_tree::MerkleTreeWithHistory;
+
+    #[test]
+    fn test_zeros() {
+        let mt = MerkleTreeWithHistory::new(20);
+        assert_eq!(ZEROS[0], mt.ZERO_VALUE);
+        for i in 1..ZEROS.len() {
+            assert_eq!(ZEROS[i], mt.hash_left_right(&ZEROS[i - 1], &ZEROS[i - 1]).unwrap());
+        }
+        assert_eq!(ZEROS[20], mt.get_last_root());
+    }
+}